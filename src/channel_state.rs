@@ -0,0 +1,134 @@
+use crate::message_parser::MessageResponse;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Channel state keyed by channel name, with a # in front.
+pub type ChannelStates = Arc<Mutex<HashMap<String, ChannelState>>>;
+
+/// Room modes from ROOMSTATE and the bot's own badges from USERSTATE.
+#[derive(Default, Debug, Clone)]
+pub struct ChannelState {
+    pub slow: Duration,
+    /// Minutes a user must have followed to chat, `None` when followers-only is off.
+    pub followers_only: Option<u64>,
+    pub emote_only: bool,
+    pub subs_only: bool,
+    pub r9k: bool,
+    pub is_broadcaster: bool,
+    pub is_moderator: bool,
+    pub is_vip: bool,
+    pub is_subscriber: bool,
+    pub last_sent: Option<(Instant, String)>,
+    /// Chat messages seen since joining, used by timers
+    pub chat_lines: u64,
+}
+
+pub enum SendDecision {
+    Send,
+    Delay(Duration),
+    Drop(&'static str),
+}
+
+impl ChannelState {
+    /// Broadcasters and moderators are exempt from every room mode.
+    fn is_privileged(&self) -> bool {
+        self.is_broadcaster || self.is_moderator
+    }
+
+    pub fn check_send(&self, now: Instant) -> SendDecision {
+        if self.is_privileged() {
+            return SendDecision::Send;
+        }
+        if self.emote_only {
+            return SendDecision::Drop("channel is in emote-only mode");
+        }
+        if self.subs_only && !self.is_subscriber {
+            return SendDecision::Drop("channel is in subscribers-only mode");
+        }
+        if !self.is_vip && !self.slow.is_zero() {
+            if let Some((sent_at, _)) = &self.last_sent {
+                let ready_at = *sent_at + self.slow;
+                if ready_at > now {
+                    return SendDecision::Delay(ready_at - now);
+                }
+            }
+        }
+
+        SendDecision::Send
+    }
+
+    /// Whether r9k mode would reject the message for being identical to the previous one.
+    pub fn is_r9k_duplicate(&self, msg: &str) -> bool {
+        self.r9k
+            && self
+                .last_sent
+                .as_ref()
+                .is_some_and(|(_, last)| message_text(last) == message_text(msg))
+    }
+}
+
+/// Updates the channel state from ROOMSTATE and USERSTATE messages, and
+/// counts chat messages.
+pub fn update(channel_states: &ChannelStates, parsed_message: &MessageResponse) {
+    let Some(command) = &parsed_message.command else {
+        return;
+    };
+    let (Some(name), Some(channel)) = (&command.command, &command.channel) else {
+        return;
+    };
+    let tags = parsed_message.tags.as_ref();
+    let mut channel_states = channel_states.lock().unwrap();
+
+    match name.as_str() {
+        "ROOMSTATE" => {
+            let state = channel_states.entry(channel.clone()).or_default();
+            // Only the changed modes are sent after the initial ROOMSTATE
+            let Some(other) = tags.and_then(|t| t.other.as_ref()) else {
+                return;
+            };
+            for (key, value) in other.iter() {
+                match key.as_str() {
+                    "slow" => state.slow = Duration::from_secs(value.parse().unwrap_or(0)),
+                    "followers-only" => state.followers_only = value.parse().ok(),
+                    "emote-only" => state.emote_only = value == "1",
+                    "subs-only" => state.subs_only = value == "1",
+                    "r9k" => state.r9k = value == "1",
+                    _ => {}
+                }
+            }
+        }
+        "USERSTATE" => {
+            let state = channel_states.entry(channel.clone()).or_default();
+            let badges = tags.and_then(|t| t.badges.as_ref());
+            let has_badge = |badge: &str| badges.is_some_and(|b| b.contains_key(badge));
+            let is_mod_tag = tags
+                .and_then(|t| t.other.as_ref())
+                .and_then(|o| o.get("mod"))
+                .is_some_and(|m| m == "1");
+            state.is_broadcaster = has_badge("broadcaster");
+            state.is_moderator = is_mod_tag || has_badge("moderator");
+            state.is_vip = has_badge("vip");
+            state.is_subscriber = has_badge("subscriber") || has_badge("founder");
+        }
+        "PRIVMSG" => {
+            // Only channels the bot is in, so a parted channel isn't added back
+            if let Some(state) = channel_states.get_mut(channel) {
+                state.chat_lines += 1;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the text of a PRIVMSG, without the tags and command.
+fn message_text(irc_message: &str) -> &str {
+    let without_tags = match irc_message.strip_prefix('@') {
+        Some(rest) => rest.split_once(' ').map(|(_, c)| c).unwrap_or_default(),
+        None => irc_message,
+    };
+    without_tags
+        .split_once(" :")
+        .map(|(_, text)| text)
+        .unwrap_or(without_tags)
+}
@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq)]
+enum State {
+    Closed {
+        failures: u32,
+    },
+    /// Calls are rejected until `until`, after which one trial call is let through
+    Open {
+        until: Instant,
+    },
+    /// A trial call is in flight, other calls are rejected until it finishes.
+    /// Another trial is let through after `until` in case it never does
+    HalfOpen {
+        until: Instant,
+    },
+}
+
+/// Stops calling a backend after `failure_threshold` consecutive failures, and
+/// only tries again once `open_duration` has passed.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    state: State,
+    failure_threshold: u32,
+    open_duration: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_duration: Duration) -> CircuitBreaker {
+        CircuitBreaker {
+            state: State::Closed { failures: 0 },
+            failure_threshold: failure_threshold.max(1),
+            open_duration,
+        }
+    }
+
+    /// Whether a call may be made now. Lets a single trial call through once
+    /// the breaker has been open for `open_duration`.
+    pub fn allow(&mut self, now: Instant) -> bool {
+        match self.state {
+            State::Closed { .. } => true,
+            State::Open { until } | State::HalfOpen { until } if now >= until => {
+                self.state = State::HalfOpen {
+                    until: now + self.open_duration,
+                };
+                true
+            }
+            State::Open { .. } | State::HalfOpen { .. } => false,
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.state = State::Closed { failures: 0 };
+    }
+
+    pub fn record_failure(&mut self, now: Instant) {
+        self.state = match self.state {
+            State::Closed { failures } if failures + 1 < self.failure_threshold => State::Closed {
+                failures: failures + 1,
+            },
+            _ => State::Open {
+                until: now + self.open_duration,
+            },
+        };
+    }
+
+    pub fn is_open(&self) -> bool {
+        !matches!(self.state, State::Closed { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let mut breaker = CircuitBreaker::new(3, Duration::from_secs(30));
+        let now = Instant::now();
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        breaker.record_success();
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        assert!(breaker.allow(now));
+
+        breaker.record_failure(now);
+        assert!(breaker.is_open());
+        assert!(!breaker.allow(now + Duration::from_secs(29)));
+    }
+
+    #[test]
+    fn lets_one_trial_call_through_after_open_duration() {
+        let mut breaker = CircuitBreaker::new(1, Duration::from_secs(30));
+        let now = Instant::now();
+        breaker.record_failure(now);
+
+        let later = now + Duration::from_secs(30);
+        assert!(breaker.allow(later));
+        assert!(!breaker.allow(later));
+
+        breaker.record_success();
+        assert!(!breaker.is_open());
+        assert!(breaker.allow(later));
+    }
+
+    #[test]
+    fn failed_trial_call_reopens() {
+        let mut breaker = CircuitBreaker::new(1, Duration::from_secs(30));
+        let now = Instant::now();
+        breaker.record_failure(now);
+
+        let later = now + Duration::from_secs(30);
+        assert!(breaker.allow(later));
+        breaker.record_failure(later);
+        assert!(!breaker.allow(later + Duration::from_secs(29)));
+        assert!(breaker.allow(later + Duration::from_secs(30)));
+    }
+}
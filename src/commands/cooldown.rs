@@ -0,0 +1,114 @@
+use super::Role;
+use crate::config::CommandsConfig;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Per-user entries older than this are pruned once the map grows past MAX_USER_ENTRIES
+const MAX_USER_COOLDOWN: Duration = Duration::from_secs(3600);
+const MAX_USER_ENTRIES: usize = 1000;
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default)]
+pub struct Cooldown {
+    /// Seconds between uses of the command in a channel
+    pub global: u64,
+    /// Seconds between uses of the command by the same user
+    pub user: u64,
+    /// Whether moderators and the broadcaster ignore the cooldown
+    pub mod_bypass: bool,
+}
+
+/// Cooldown overrides of a channel.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ChannelCooldowns {
+    /// Reply with the remaining time instead of silently ignoring the command
+    pub reply: Option<bool>,
+    /// Overrides keyed by command name
+    pub commands: HashMap<String, Cooldown>,
+}
+
+pub enum CooldownCheck {
+    Ready,
+    OnCooldown { remaining: Duration, reply: bool },
+}
+
+pub struct Cooldowns {
+    defaults: Cooldown,
+    reply: bool,
+    last_used: Mutex<HashMap<(String, String), Instant>>,
+    last_used_by_user: Mutex<HashMap<(String, String, String), Instant>>,
+}
+
+impl Cooldowns {
+    /// Bot-wide defaults for commands that don't define their own cooldown.
+    pub fn new(config: &CommandsConfig) -> Cooldowns {
+        Cooldowns {
+            defaults: Cooldown {
+                global: config.global_cooldown,
+                user: config.user_cooldown,
+                mod_bypass: config.cooldown_mod_bypass,
+            },
+            reply: config.cooldown_reply,
+            last_used: Mutex::new(HashMap::new()),
+            last_used_by_user: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Marks the command as used by the user, unless it is still on cooldown.
+    /// `command_cooldown` is the command's own default, if it has one.
+    pub fn check(
+        &self,
+        channel_settings: &ChannelCooldowns,
+        channel_name: &str,
+        command_name: &str,
+        command_cooldown: Option<Cooldown>,
+        username: &str,
+        role: Role,
+    ) -> CooldownCheck {
+        let cooldown = channel_settings
+            .commands
+            .get(command_name)
+            .copied()
+            .or(command_cooldown)
+            .unwrap_or(self.defaults);
+        let reply = channel_settings.reply.unwrap_or(self.reply);
+
+        if cooldown.mod_bypass && role >= Role::Moderator {
+            return CooldownCheck::Ready;
+        }
+
+        let now = Instant::now();
+        let channel_key = (channel_name.to_string(), command_name.to_string());
+        let user_key = (
+            channel_name.to_string(),
+            command_name.to_string(),
+            username.to_lowercase(),
+        );
+        let mut last_used = self.last_used.lock().unwrap();
+        let mut last_used_by_user = self.last_used_by_user.lock().unwrap();
+
+        // Subtracts instead of adding to the Instant, which would overflow on
+        // huge cooldowns from stbot-web
+        let remaining = |used_at: Option<&Instant>, secs: u64| {
+            used_at
+                .map(|u| Duration::from_secs(secs).saturating_sub(now.duration_since(*u)))
+                .unwrap_or_default()
+        };
+        let remaining = remaining(last_used.get(&channel_key), cooldown.global)
+            .max(remaining(last_used_by_user.get(&user_key), cooldown.user));
+        if !remaining.is_zero() {
+            return CooldownCheck::OnCooldown { remaining, reply };
+        }
+
+        if last_used_by_user.len() > MAX_USER_ENTRIES {
+            last_used_by_user.retain(|_, used_at| now.duration_since(*used_at) < MAX_USER_COOLDOWN);
+        }
+        last_used.insert(channel_key, now);
+        last_used_by_user.insert(user_key, now);
+
+        CooldownCheck::Ready
+    }
+}
@@ -0,0 +1,218 @@
+use super::{status_reply, BotCommand, CommandContext, Cooldown, Reply, Role};
+use crate::messages;
+use async_trait::async_trait;
+use serde::Deserialize;
+
+const MAX_NAME_LENGTH: usize = 25;
+
+/// A text command a channel defined itself, like ?discord.
+#[derive(Deserialize, Debug, Clone)]
+pub struct CustomCommand {
+    /// Name without the prefix, in lowercase
+    pub name: String,
+    /// Supports {user}, {channel}, {song}, {count} and {args}
+    pub response: String,
+    #[serde(default)]
+    pub role: Role,
+    /// Cooldown unless overridden for the channel, otherwise the bot-wide defaults
+    #[serde(default)]
+    pub cooldown: Option<Cooldown>,
+}
+
+/// Replies with the command's response, with its variables filled in. The
+/// song and count are only looked up if the response uses them.
+pub async fn execute(
+    command: &CustomCommand,
+    ctx: &CommandContext<'_>,
+) -> Result<Option<Reply>, String> {
+    let response = &command.response;
+    let song = if response.contains("{song}") {
+        let song = ctx
+            .songs
+            .get(ctx.web, ctx.channel_name)
+            .await
+            .map_err(|e| format!("Could not get song for {}: {}", command.name, e))?;
+        if song.is_playing {
+            song.item.title()
+        } else {
+            ctx.settings.message("song.not_playing", &[])
+        }
+    } else {
+        String::new()
+    };
+    let count = if response.contains("{count}") {
+        ctx.web
+            .count_custom_command(ctx.channel_name, &command.name)
+            .await
+            .map_err(|e| format!("Could not count use of {}: {}", command.name, e))?
+            .to_string()
+    } else {
+        String::new()
+    };
+
+    let args = ctx.args.join(" ");
+    let message = messages::format(
+        response,
+        &[
+            ("user", ctx.display_name),
+            ("channel", ctx.channel_name),
+            ("song", &song),
+            ("count", &count),
+            ("args", args.trim()),
+        ],
+    );
+
+    Ok(Some(Reply::message(message)))
+}
+
+/// Normalizes the name of a command to add, edit or delete, which may be
+/// given with or without the channel's prefix.
+fn parse_name(name: &str, prefix: &str) -> Option<String> {
+    let name = name.strip_prefix(prefix).unwrap_or(name).to_lowercase();
+    let is_valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    is_valid.then_some(name)
+}
+
+/// Parses `<name> <response>` for ?addcmd and ?editcmd.
+fn parse_args(ctx: &CommandContext<'_>) -> Option<(String, String)> {
+    let (name, response) = ctx.args.split_first()?;
+    let name = parse_name(name, &ctx.settings.prefix)?;
+    let response = response.join(" ").trim().to_string();
+    if response.is_empty() {
+        return None;
+    }
+
+    Some((name, response))
+}
+
+fn exists(ctx: &CommandContext<'_>, name: &str) -> bool {
+    ctx.settings.custom_commands.iter().any(|c| c.name == name)
+}
+
+pub struct AddCommand;
+
+#[async_trait]
+impl BotCommand for AddCommand {
+    fn name(&self) -> &'static str {
+        "addcmd"
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Moderator
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let Some((name, response)) = parse_args(ctx) else {
+            return Ok(Some(Reply::message(
+                ctx.settings.message("addcmd.usage", &[]),
+            )));
+        };
+        if ctx.registry.is_built_in(&name) {
+            return Ok(Some(Reply::message(
+                ctx.settings
+                    .message("customcmd.built_in", &[("command", &name)]),
+            )));
+        }
+        if exists(ctx, &name) {
+            return Ok(Some(Reply::message(
+                ctx.settings
+                    .message("customcmd.exists", &[("command", &name)]),
+            )));
+        }
+
+        let res = ctx
+            .web
+            .save_custom_command(ctx.channel_name, &name, &response)
+            .await;
+        ctx.registry.refresh_settings(ctx.channel_name).await;
+        status_reply(
+            ctx,
+            res,
+            "Adding command",
+            ctx.settings.message("addcmd.done", &[("command", &name)]),
+        )
+    }
+}
+
+pub struct EditCommand;
+
+#[async_trait]
+impl BotCommand for EditCommand {
+    fn name(&self) -> &'static str {
+        "editcmd"
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Moderator
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let Some((name, response)) = parse_args(ctx) else {
+            return Ok(Some(Reply::message(
+                ctx.settings.message("editcmd.usage", &[]),
+            )));
+        };
+        if !exists(ctx, &name) {
+            return Ok(Some(Reply::message(
+                ctx.settings
+                    .message("customcmd.missing", &[("command", &name)]),
+            )));
+        }
+
+        let res = ctx
+            .web
+            .save_custom_command(ctx.channel_name, &name, &response)
+            .await;
+        ctx.registry.refresh_settings(ctx.channel_name).await;
+        status_reply(
+            ctx,
+            res,
+            "Editing command",
+            ctx.settings.message("editcmd.done", &[("command", &name)]),
+        )
+    }
+}
+
+pub struct DeleteCommand;
+
+#[async_trait]
+impl BotCommand for DeleteCommand {
+    fn name(&self) -> &'static str {
+        "delcmd"
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Moderator
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let name = ctx
+            .args
+            .first()
+            .and_then(|n| parse_name(n, &ctx.settings.prefix));
+        let Some(name) = name else {
+            return Ok(Some(Reply::message(
+                ctx.settings.message("delcmd.usage", &[]),
+            )));
+        };
+        if !exists(ctx, &name) {
+            return Ok(Some(Reply::message(
+                ctx.settings
+                    .message("customcmd.missing", &[("command", &name)]),
+            )));
+        }
+
+        let res = ctx.web.delete_custom_command(ctx.channel_name, &name).await;
+        ctx.registry.refresh_settings(ctx.channel_name).await;
+        status_reply(
+            ctx,
+            res,
+            "Deleting command",
+            ctx.settings.message("delcmd.done", &[("command", &name)]),
+        )
+    }
+}
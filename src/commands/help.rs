@@ -0,0 +1,25 @@
+use super::{BotCommand, CommandContext, Cooldown, Reply};
+use async_trait::async_trait;
+
+pub struct Commands;
+
+#[async_trait]
+impl BotCommand for Commands {
+    fn name(&self) -> &'static str {
+        "commands"
+    }
+
+    fn cooldown(&self) -> Option<Cooldown> {
+        Some(Cooldown {
+            global: 5,
+            user: 15,
+            mod_bypass: true,
+        })
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        Ok(Some(Reply::message(
+            ctx.registry.help(ctx.role, ctx.settings),
+        )))
+    }
+}
@@ -0,0 +1,358 @@
+mod cooldown;
+mod custom;
+mod help;
+mod playback;
+mod queue;
+mod skip;
+mod song;
+mod song_request;
+mod timer;
+
+use crate::config::Config;
+use crate::settings::{ChannelSettings, Settings};
+use crate::song_cache::SongCache;
+use crate::song_history::SongHistory;
+use crate::vote_skip::VoteSkip;
+use crate::web::{WebClient, WebError};
+use async_trait::async_trait;
+use cooldown::{CooldownCheck, Cooldowns};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub use cooldown::{ChannelCooldowns, Cooldown};
+pub use custom::CustomCommand;
+pub use song::SongSettings;
+pub use song_request::SongRequestSettings;
+
+// Twitch drops chat messages longer than this
+const MAX_MESSAGE_LENGTH: usize = 500;
+
+/// Role of a chatter, derived from their badges. Roles are ordered so that a
+/// higher role has every permission of the roles below it.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    Viewer,
+    Subscriber,
+    Vip,
+    Moderator,
+    Broadcaster,
+}
+
+impl Role {
+    pub fn from_badges(badges: Option<&HashMap<String, String>>) -> Role {
+        let has_badge = |badge: &str| badges.is_some_and(|b| b.contains_key(badge));
+        if has_badge("broadcaster") {
+            Role::Broadcaster
+        } else if has_badge("moderator") {
+            Role::Moderator
+        } else if has_badge("vip") {
+            Role::Vip
+        } else if has_badge("subscriber") || has_badge("founder") {
+            Role::Subscriber
+        } else {
+            Role::Viewer
+        }
+    }
+}
+
+pub struct Reply {
+    pub message: String,
+}
+
+impl Reply {
+    pub fn message(message: impl Into<String>) -> Reply {
+        Reply {
+            message: message.into(),
+        }
+    }
+}
+
+pub struct CommandContext<'a> {
+    /// Channel name without a # in front
+    pub channel_name: &'a str,
+    pub display_name: &'a str,
+    pub role: Role,
+    /// Arguments after the command name
+    pub args: &'a [&'a str],
+    pub registry: &'a CommandRegistry,
+    pub web: &'a WebClient,
+    pub songs: &'a SongCache,
+    pub settings: &'a ChannelSettings,
+}
+
+#[async_trait]
+pub trait BotCommand: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Viewer
+    }
+
+    /// Default cooldown, unless overridden for the channel. Commands without
+    /// one use the bot-wide defaults.
+    fn cooldown(&self) -> Option<Cooldown> {
+        None
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String>;
+}
+
+pub struct CommandRegistry {
+    commands: Vec<Box<dyn BotCommand>>,
+    cooldowns: Cooldowns,
+    web: Arc<WebClient>,
+    songs: Arc<SongCache>,
+    settings: Arc<Settings>,
+}
+
+impl CommandRegistry {
+    pub fn new(
+        config: &Config,
+        vote_skip: Arc<VoteSkip>,
+        history: Arc<SongHistory>,
+        songs: Arc<SongCache>,
+        settings: Arc<Settings>,
+        web: Arc<WebClient>,
+    ) -> CommandRegistry {
+        let mut registry = CommandRegistry {
+            commands: Vec::new(),
+            cooldowns: Cooldowns::new(&config.commands),
+            web,
+            songs,
+            settings,
+        };
+        registry.register(song::Song);
+        registry.register(song::SongLink);
+        registry.register(queue::Queue);
+        registry.register(queue::LastSong {
+            history: history.clone(),
+        });
+        registry.register(queue::History { history });
+        registry.register(song_request::SongRequest::new());
+        registry.register(skip::Skip {
+            vote_skip: vote_skip.clone(),
+        });
+        registry.register(skip::SkipStatus {
+            vote_skip: vote_skip.clone(),
+        });
+        registry.register(skip::ForceSkip {
+            vote_skip: vote_skip.clone(),
+        });
+        registry.register(skip::VetoSkip { vote_skip });
+        registry.register(skip::SkipOn);
+        registry.register(skip::SkipOff);
+        registry.register(playback::Pause);
+        registry.register(playback::Resume);
+        registry.register(playback::Volume);
+        registry.register(playback::Previous);
+        registry.register(playback::Like);
+        registry.register(custom::AddCommand);
+        registry.register(custom::EditCommand);
+        registry.register(custom::DeleteCommand);
+        registry.register(timer::ManageTimers);
+        registry.register(help::Commands);
+
+        registry
+    }
+
+    /// Fetches the channel's settings ahead of its first command.
+    pub async fn refresh_settings(&self, channel_name: &str) {
+        self.settings.refresh(&self.web, channel_name).await;
+    }
+
+    pub fn register(&mut self, command: impl BotCommand + 'static) {
+        self.commands.push(Box::new(command));
+    }
+
+    fn find(&self, name: &str) -> Option<&dyn BotCommand> {
+        self.commands
+            .iter()
+            .find(|c| c.name() == name || c.aliases().contains(&name))
+            .map(|c| c.as_ref())
+    }
+
+    /// Whether `name` is the name or alias of a command of the bot itself.
+    fn is_built_in(&self, name: &str) -> bool {
+        self.find(name).is_some()
+    }
+
+    /// Lists every enabled command and alias available to the given role,
+    /// including the channel's custom commands, as many as fit in a chat message.
+    pub fn help(&self, role: Role, settings: &ChannelSettings) -> String {
+        let built_in = self
+            .commands
+            .iter()
+            .filter(|c| c.required_role() <= role && !settings.is_disabled(c.name()))
+            .flat_map(|c| std::iter::once(c.name()).chain(c.aliases().iter().copied()));
+        let custom = settings
+            .custom_commands
+            .iter()
+            .filter(|c| c.role <= role && !settings.is_disabled(&c.name))
+            .map(|c| c.name.as_str());
+        let names = built_in
+            .chain(custom)
+            .map(|name| format!("{}{}", settings.prefix, name))
+            .collect();
+        join_to_fit(settings.message("help.list", &[]), names)
+    }
+
+    pub async fn dispatch(
+        &self,
+        user_msg: &str,
+        channel_name: &str,
+        username: &str,
+        role: Role,
+    ) -> Result<Option<Reply>, String> {
+        let settings = self.settings.get(&self.web, channel_name).await;
+        let tokens: Vec<&str> = user_msg.split(' ').collect();
+        // An empty prefix would turn every chat message into a command
+        let name = tokens
+            .first()
+            .filter(|_| !settings.prefix.is_empty())
+            .and_then(|t| t.strip_prefix(settings.prefix.as_str()));
+        let Some(name) = name else {
+            return Ok(None);
+        };
+        let command = match self.find(name) {
+            Some(command) => Target::BuiltIn(command),
+            None => match settings.custom_commands.iter().find(|c| c.name == name) {
+                Some(command) => Target::Custom(command),
+                None => return Ok(None),
+            },
+        };
+        if role < command.required_role() {
+            return Err(format!(
+                "{} requires role {:?}, user has {:?}.",
+                command.name(),
+                command.required_role(),
+                role
+            ));
+        }
+        if settings.is_disabled(command.name()) {
+            return Ok(None);
+        }
+        let cooldown = self.cooldowns.check(
+            &settings.cooldowns,
+            channel_name,
+            command.name(),
+            command.cooldown(),
+            username,
+            role,
+        );
+        if let CooldownCheck::OnCooldown { remaining, reply } = cooldown {
+            if !reply {
+                return Ok(None);
+            }
+            return Ok(Some(Reply::message(settings.message(
+                "cooldown",
+                &[
+                    ("command", command.name()),
+                    (
+                        "seconds",
+                        &remaining.as_secs().saturating_add(1).to_string(),
+                    ),
+                ],
+            ))));
+        }
+
+        let ctx = CommandContext {
+            channel_name,
+            display_name: username,
+            role,
+            args: &tokens[1..],
+            registry: self,
+            web: &self.web,
+            songs: &self.songs,
+            settings: &settings,
+        };
+        let res = match command {
+            Target::BuiltIn(command) => command.execute(&ctx).await,
+            Target::Custom(command) => custom::execute(command, &ctx).await,
+        };
+        if let Err(e) = &res {
+            if !self.web.is_available() {
+                eprintln!("[ERROR] {}", e);
+                return Ok(Some(Reply::message(settings.message("unavailable", &[]))));
+            }
+        }
+        res
+    }
+}
+
+/// A command found by `dispatch`.
+enum Target<'a> {
+    BuiltIn(&'a dyn BotCommand),
+    Custom(&'a CustomCommand),
+}
+
+impl Target<'_> {
+    fn name(&self) -> &str {
+        match self {
+            Target::BuiltIn(command) => command.name(),
+            Target::Custom(command) => &command.name,
+        }
+    }
+
+    fn required_role(&self) -> Role {
+        match self {
+            Target::BuiltIn(command) => command.required_role(),
+            Target::Custom(command) => command.role,
+        }
+    }
+
+    fn cooldown(&self) -> Option<Cooldown> {
+        match self {
+            Target::BuiltIn(command) => command.cooldown(),
+            Target::Custom(command) => command.cooldown,
+        }
+    }
+}
+
+/// Replies with the `success` message if stbot-web accepted the request, or
+/// with the status code it rejected the request with. Other errors are
+/// returned, describing what `action` failed with.
+fn status_reply(
+    ctx: &CommandContext<'_>,
+    res: Result<(), WebError>,
+    action: &str,
+    success: String,
+) -> Result<Option<Reply>, String> {
+    match res {
+        Ok(()) => Ok(Some(Reply::message(success))),
+        Err(WebError::Status(s)) => {
+            eprintln!("[ERROR] {} failed with status code {}", action, s);
+            Ok(Some(Reply::message(
+                ctx.settings
+                    .message("request.failed", &[("status", s.as_str())]),
+            )))
+        }
+        Err(e) => Err(format!("{} failed: {}", action, e)),
+    }
+}
+
+/// Joins as many items as fit in a single chat message after `prefix`.
+fn join_to_fit(prefix: String, items: Vec<String>) -> String {
+    const SEPARATOR: &str = " | ";
+    const ELLIPSIS: &str = " ...";
+    let mut message = prefix;
+    for (i, item) in items.iter().enumerate() {
+        let separator = if i == 0 { " " } else { SEPARATOR };
+        let is_last = i == items.len() - 1;
+        let reserved = if is_last { 0 } else { ELLIPSIS.len() };
+        if message.len() + separator.len() + item.len() + reserved > MAX_MESSAGE_LENGTH {
+            message.push_str(ELLIPSIS);
+            break;
+        }
+        message.push_str(separator);
+        message.push_str(item);
+    }
+
+    message
+}
@@ -0,0 +1,136 @@
+use super::{status_reply, BotCommand, CommandContext, Reply, Role};
+use async_trait::async_trait;
+
+pub struct Pause;
+
+#[async_trait]
+impl BotCommand for Pause {
+    fn name(&self) -> &'static str {
+        "pause"
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Moderator
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let res = ctx.web.pause(ctx.channel_name).await;
+        ctx.songs.invalidate(ctx.channel_name);
+        status_reply(
+            ctx,
+            res,
+            "Pausing playback",
+            ctx.settings.message("pause.done", &[]),
+        )
+    }
+}
+
+pub struct Resume;
+
+#[async_trait]
+impl BotCommand for Resume {
+    fn name(&self) -> &'static str {
+        "resume"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["play"]
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Moderator
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let res = ctx.web.resume(ctx.channel_name).await;
+        ctx.songs.invalidate(ctx.channel_name);
+        status_reply(
+            ctx,
+            res,
+            "Resuming playback",
+            ctx.settings.message("resume.done", &[]),
+        )
+    }
+}
+
+pub struct Volume;
+
+#[async_trait]
+impl BotCommand for Volume {
+    fn name(&self) -> &'static str {
+        "volume"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["vol"]
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Moderator
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let volume = ctx
+            .args
+            .first()
+            .and_then(|v| v.trim_end_matches('%').parse::<u8>().ok())
+            .filter(|v| *v <= 100);
+        let Some(volume) = volume else {
+            return Ok(Some(Reply::message(
+                ctx.settings.message("volume.usage", &[]),
+            )));
+        };
+        let res = ctx.web.set_volume(ctx.channel_name, volume).await;
+        let success = ctx
+            .settings
+            .message("volume.set", &[("volume", &volume.to_string())]);
+        status_reply(ctx, res, "Setting volume", success)
+    }
+}
+
+pub struct Previous;
+
+#[async_trait]
+impl BotCommand for Previous {
+    fn name(&self) -> &'static str {
+        "previous"
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Moderator
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let res = ctx.web.previous(ctx.channel_name).await;
+        ctx.songs.invalidate(ctx.channel_name);
+        status_reply(
+            ctx,
+            res,
+            "Going back to the previous song",
+            ctx.settings.message("previous.done", &[]),
+        )
+    }
+}
+
+pub struct Like;
+
+#[async_trait]
+impl BotCommand for Like {
+    fn name(&self) -> &'static str {
+        "like"
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Broadcaster
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let res = ctx.web.like(ctx.channel_name).await;
+        status_reply(
+            ctx,
+            res,
+            "Saving song",
+            ctx.settings.message("like.done", &[]),
+        )
+    }
+}
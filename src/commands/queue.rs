@@ -0,0 +1,106 @@
+use super::{join_to_fit, BotCommand, CommandContext, Reply};
+use crate::song_history::SongHistory;
+use crate::web::{Item, WebClient};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+const MAX_LISTED_TRACKS: usize = 5;
+
+pub struct Queue;
+
+#[async_trait]
+impl BotCommand for Queue {
+    fn name(&self) -> &'static str {
+        "queue"
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let queue = ctx
+            .web
+            .queue(ctx.channel_name)
+            .await
+            .map_err(|e| format!("Could not get queue: {}", e))?;
+        if queue.is_empty() {
+            return Ok(Some(Reply::message(
+                ctx.settings.message("queue.empty", &[]),
+            )));
+        }
+        let titles = queue
+            .iter()
+            .take(MAX_LISTED_TRACKS)
+            .enumerate()
+            .map(|(i, t)| format!("{}. {}", i + 1, t.title()))
+            .collect();
+
+        Ok(Some(Reply::message(join_to_fit(
+            ctx.settings.message("queue.list", &[]),
+            titles,
+        ))))
+    }
+}
+
+pub struct LastSong {
+    pub history: Arc<SongHistory>,
+}
+
+#[async_trait]
+impl BotCommand for LastSong {
+    fn name(&self) -> &'static str {
+        "lastsong"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["prevsong"]
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let recent = recent_titles(ctx.web, &self.history, ctx.channel_name).await;
+        match recent.first() {
+            Some(t) => Ok(Some(Reply::message(
+                ctx.settings.message("lastsong.found", &[("song", t)]),
+            ))),
+            None => Ok(Some(Reply::message(
+                ctx.settings.message("lastsong.none", &[]),
+            ))),
+        }
+    }
+}
+
+pub struct History {
+    pub history: Arc<SongHistory>,
+}
+
+#[async_trait]
+impl BotCommand for History {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let recent = recent_titles(ctx.web, &self.history, ctx.channel_name).await;
+        if recent.is_empty() {
+            return Ok(Some(Reply::message(
+                ctx.settings.message("history.none", &[]),
+            )));
+        }
+        let titles = recent.into_iter().take(MAX_LISTED_TRACKS).collect();
+
+        Ok(Some(Reply::message(join_to_fit(
+            ctx.settings.message("history.list", &[]),
+            titles,
+        ))))
+    }
+}
+
+/// Previously played tracks, most recent first. Falls back to the tracks the
+/// bot has seen itself if stbot-web can't be reached.
+async fn recent_titles(web: &WebClient, history: &SongHistory, channel_name: &str) -> Vec<String> {
+    match web.recent_tracks(channel_name).await {
+        Ok(tracks) => tracks.iter().map(Item::title).collect(),
+        Err(e) => {
+            eprintln!("[ERROR] Could not get recent tracks: {}", e);
+            // The most recently seen track is most likely still playing
+            history.recent(channel_name).into_iter().skip(1).collect()
+        }
+    }
+}
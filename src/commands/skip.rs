@@ -0,0 +1,285 @@
+use super::{status_reply, BotCommand, CommandContext, Reply, Role};
+use crate::vote_skip::{VoteOutcome, VoteSkip, MAX_SKIP_SECS};
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Instant;
+
+pub struct Skip {
+    pub vote_skip: Arc<VoteSkip>,
+}
+
+#[async_trait]
+impl BotCommand for Skip {
+    fn name(&self) -> &'static str {
+        "skip"
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        if !ctx.settings.skip.enabled {
+            return Ok(None);
+        }
+        let song = ctx
+            .songs
+            .get(ctx.web, ctx.channel_name)
+            .await
+            .map_err(|e| format!("Could not get song to skip: {}", e))?;
+        if !song.is_playing {
+            return Ok(None);
+        }
+
+        let channel = format!("#{}", ctx.channel_name);
+        let settings = &ctx.settings.skip;
+        let active_chatters = self
+            .vote_skip
+            .active_chatters
+            .lock()
+            .unwrap()
+            .count(&channel);
+        let outcome = self.vote_skip.sessions.lock().unwrap().vote(
+            &channel,
+            &song.item.track_id(),
+            ctx.display_name,
+            settings,
+            active_chatters,
+            Instant::now(),
+        );
+        match outcome {
+            VoteOutcome::OnCooldown(remaining) => Ok(Some(Reply::message(ctx.settings.message(
+                "skip.vote_cooldown",
+                &[("seconds", &(remaining.as_secs() + 1).to_string())],
+            )))),
+            VoteOutcome::AlreadyVoted { .. } => Ok(None),
+            VoteOutcome::Counted { votes, required } if votes < required => {
+                Ok(Some(Reply::message(ctx.settings.message(
+                    "skip.votes",
+                    &[
+                        ("votes", &votes.to_string()),
+                        ("required", &required.to_string()),
+                        ("title", &song.item.name),
+                    ],
+                ))))
+            }
+            VoteOutcome::Counted { .. } => {
+                skip_song(&self.vote_skip, ctx, "skip.vote_passed").await
+            }
+        }
+    }
+}
+
+pub struct ForceSkip {
+    pub vote_skip: Arc<VoteSkip>,
+}
+
+#[async_trait]
+impl BotCommand for ForceSkip {
+    fn name(&self) -> &'static str {
+        "forceskip"
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Moderator
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        skip_song(&self.vote_skip, ctx, "skip.skipped").await
+    }
+}
+
+pub struct VetoSkip {
+    pub vote_skip: Arc<VoteSkip>,
+}
+
+#[async_trait]
+impl BotCommand for VetoSkip {
+    fn name(&self) -> &'static str {
+        "vetoskip"
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Moderator
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let channel = format!("#{}", ctx.channel_name);
+        let vetoed = self
+            .vote_skip
+            .sessions
+            .lock()
+            .unwrap()
+            .veto(&channel, Instant::now());
+        if !vetoed {
+            return Ok(Some(Reply::message(
+                ctx.settings.message("skip.no_vote", &[]),
+            )));
+        }
+
+        Ok(Some(Reply::message(
+            ctx.settings.message("skip.vetoed", &[]),
+        )))
+    }
+}
+
+pub struct SkipStatus {
+    pub vote_skip: Arc<VoteSkip>,
+}
+
+#[async_trait]
+impl BotCommand for SkipStatus {
+    fn name(&self) -> &'static str {
+        "skipstatus"
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        if !ctx.settings.skip.enabled {
+            return Ok(None);
+        }
+        let song = ctx
+            .songs
+            .get(ctx.web, ctx.channel_name)
+            .await
+            .map_err(|e| format!("Could not get song for skip status: {}", e))?;
+        let channel = format!("#{}", ctx.channel_name);
+        let votes = self.vote_skip.sessions.lock().unwrap().votes(
+            &channel,
+            &song.item.track_id(),
+            Instant::now(),
+        );
+        if votes == 0 {
+            return Ok(Some(Reply::message(
+                ctx.settings.message("skip.no_vote", &[]),
+            )));
+        }
+        let required = self.vote_skip.required_votes(&channel, &ctx.settings.skip);
+
+        Ok(Some(Reply::message(ctx.settings.message(
+            "skip.status",
+            &[
+                ("votes", &votes.to_string()),
+                ("required", &required.to_string()),
+            ],
+        ))))
+    }
+}
+
+/// Skips the current song and resets the channel's vote skip session. Replies
+/// with the message named `success` if it worked.
+async fn skip_song(
+    vote_skip: &VoteSkip,
+    ctx: &CommandContext<'_>,
+    success: &str,
+) -> Result<Option<Reply>, String> {
+    let res = ctx.web.skip(ctx.channel_name).await;
+    if res.is_ok() {
+        ctx.songs.invalidate(ctx.channel_name);
+        vote_skip
+            .sessions
+            .lock()
+            .unwrap()
+            .skipped(&format!("#{}", ctx.channel_name), Instant::now());
+    }
+    status_reply(
+        ctx,
+        res,
+        "Skipping song",
+        ctx.settings.message(success, &[]),
+    )
+}
+
+pub struct SkipOn;
+
+#[async_trait]
+impl BotCommand for SkipOn {
+    fn name(&self) -> &'static str {
+        "skipon"
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Broadcaster
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let Some(params) = parse_skip_params(ctx.args) else {
+            return Ok(Some(Reply::message(
+                ctx.settings.message("skipon.usage", &[]),
+            )));
+        };
+        let res = ctx.web.enable_song_skip(ctx.channel_name, &params).await;
+        ctx.registry
+            .settings
+            .refresh(ctx.web, ctx.channel_name)
+            .await;
+        status_reply(
+            ctx,
+            res,
+            "Enabling song skip",
+            ctx.settings.message("skipon.enabled", &[]),
+        )
+    }
+}
+
+/// Parses the optional `?skipon` arguments into stbot-web query params.
+fn parse_skip_params(args: &[&str]) -> Option<Vec<(&'static str, String)>> {
+    let mut params = Vec::new();
+    let mut args = args.iter().filter(|a| !a.is_empty());
+    if let Some(votes) = args.next() {
+        match votes.strip_suffix('%') {
+            Some(percentage) => {
+                let percentage = percentage
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|p| (1..=100).contains(p))?;
+                params.push(("required_percentage", percentage.to_string()));
+            }
+            None => {
+                let votes = votes.parse::<u32>().ok().filter(|v| *v > 0)?;
+                params.push(("required_votes", votes.to_string()));
+            }
+        }
+    }
+    if let Some(lifetime) = args.next() {
+        let lifetime = lifetime
+            .parse::<u64>()
+            .ok()
+            .filter(|l| (1..=MAX_SKIP_SECS).contains(l))?;
+        params.push(("vote_lifetime", lifetime.to_string()));
+    }
+    if let Some(cooldown) = args.next() {
+        let cooldown = cooldown
+            .parse::<u64>()
+            .ok()
+            .filter(|c| *c <= MAX_SKIP_SECS)?;
+        params.push(("cooldown", cooldown.to_string()));
+    }
+    if args.next().is_some() {
+        return None;
+    }
+
+    Some(params)
+}
+
+pub struct SkipOff;
+
+#[async_trait]
+impl BotCommand for SkipOff {
+    fn name(&self) -> &'static str {
+        "skipoff"
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Broadcaster
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let res = ctx.web.disable_song_skip(ctx.channel_name).await;
+        ctx.registry
+            .settings
+            .refresh(ctx.web, ctx.channel_name)
+            .await;
+        status_reply(
+            ctx,
+            res,
+            "Disabling song skip",
+            ctx.settings.message("skipoff.disabled", &[]),
+        )
+    }
+}
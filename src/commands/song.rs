@@ -0,0 +1,131 @@
+use super::{BotCommand, CommandContext, Cooldown, Reply};
+use crate::messages;
+use crate::web::SongResponse;
+use async_trait::async_trait;
+use serde::Deserialize;
+
+/// ?song settings of a channel.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SongSettings {
+    /// Output of ?song. Supports {title}, {artists}, {artist}, {album},
+    /// {progress}, {duration} and {link}
+    pub template: String,
+}
+
+impl Default for SongSettings {
+    fn default() -> Self {
+        SongSettings {
+            template: "{artists} - {title}".to_string(),
+        }
+    }
+}
+
+pub struct Song;
+
+#[async_trait]
+impl BotCommand for Song {
+    fn name(&self) -> &'static str {
+        "song"
+    }
+
+    fn cooldown(&self) -> Option<Cooldown> {
+        Some(Cooldown {
+            global: 5,
+            user: 15,
+            mod_bypass: true,
+        })
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let s = ctx
+            .songs
+            .get(ctx.web, ctx.channel_name)
+            .await
+            .map_err(|e| format!("Could not get song: {}", e))?;
+        if !s.is_playing {
+            return Ok(Some(Reply::message(
+                ctx.settings.message("song.not_playing", &[]),
+            )));
+        }
+
+        Ok(Some(Reply::message(format_song(
+            &ctx.settings.song.template,
+            &s,
+        ))))
+    }
+}
+
+pub struct SongLink;
+
+#[async_trait]
+impl BotCommand for SongLink {
+    fn name(&self) -> &'static str {
+        "songlink"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["slink"]
+    }
+
+    fn cooldown(&self) -> Option<Cooldown> {
+        Some(Cooldown {
+            global: 5,
+            user: 15,
+            mod_bypass: true,
+        })
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let s = ctx
+            .songs
+            .get(ctx.web, ctx.channel_name)
+            .await
+            .map_err(|e| format!("Could not get song: {}", e))?;
+        if !s.is_playing {
+            return Ok(Some(Reply::message(
+                ctx.settings.message("song.not_playing", &[]),
+            )));
+        }
+
+        match &s.item.external_urls.spotify {
+            Some(link) if !s.item.is_local => Ok(Some(Reply::message(link))),
+            _ => Ok(Some(Reply::message(
+                ctx.settings
+                    .message("songlink.local", &[("title", &s.item.name)]),
+            ))),
+        }
+    }
+}
+
+/// Fills in the placeholders of a ?song template.
+fn format_song(template: &str, song: &SongResponse) -> String {
+    let item = &song.item;
+    let artists = item.artist_names();
+    let first_artist = item.artists.first().map(|a| a.name.as_str());
+    messages::format(
+        template,
+        &[
+            ("title", &item.name),
+            ("artists", &artists),
+            ("artist", first_artist.unwrap_or(&artists)),
+            ("album", item.album_name()),
+            ("progress", &format_duration(song.progress_ms)),
+            ("duration", &format_duration(item.duration_ms)),
+            (
+                "link",
+                item.external_urls.spotify.as_deref().unwrap_or_default(),
+            ),
+        ],
+    )
+}
+
+/// Formats milliseconds as m:ss, or h:mm:ss for long podcast episodes.
+fn format_duration(ms: u64) -> String {
+    let secs = ms / 1000;
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
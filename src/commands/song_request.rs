@@ -0,0 +1,204 @@
+use super::{BotCommand, CommandContext, Reply, Role};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Song request settings of a channel.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SongRequestSettings {
+    pub enabled: bool,
+    /// Longest track that can be requested, in seconds
+    pub max_track_length: u64,
+    /// Requests a viewer can make within `request_window`
+    pub max_requests_per_user: usize,
+    /// Seconds a request counts towards the viewer's limit
+    pub request_window: u64,
+}
+
+impl Default for SongRequestSettings {
+    fn default() -> Self {
+        SongRequestSettings {
+            enabled: false,
+            max_track_length: 600,
+            max_requests_per_user: 3,
+            request_window: 3600,
+        }
+    }
+}
+
+pub struct SongRequest {
+    /// Times of recent requests keyed by channel and lowercase username
+    requests: Mutex<HashMap<(String, String), Vec<Instant>>>,
+}
+
+impl SongRequest {
+    pub fn new() -> SongRequest {
+        SongRequest {
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts a request towards the user's limit, unless the limit is reached.
+    fn try_request(
+        &self,
+        channel_name: &str,
+        username: &str,
+        settings: &SongRequestSettings,
+    ) -> bool {
+        let now = Instant::now();
+        let window = Duration::from_secs(settings.request_window);
+        let mut requests = self.requests.lock().unwrap();
+        requests.retain(|_, times| {
+            times.retain(|t| now.duration_since(*t) < window);
+            !times.is_empty()
+        });
+        let times = requests
+            .entry((channel_name.to_string(), username.to_lowercase()))
+            .or_default();
+        if times.len() >= settings.max_requests_per_user {
+            return false;
+        }
+        times.push(now);
+
+        true
+    }
+
+    /// Gives back a request that couldn't be queued.
+    fn undo_request(&self, channel_name: &str, username: &str) {
+        let key = (channel_name.to_string(), username.to_lowercase());
+        if let Some(times) = self.requests.lock().unwrap().get_mut(&key) {
+            times.pop();
+        }
+    }
+}
+
+#[async_trait]
+impl BotCommand for SongRequest {
+    fn name(&self) -> &'static str {
+        "sr"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["songrequest"]
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let query = ctx.args.join(" ").trim().to_string();
+        if query.is_empty() {
+            return Ok(Some(Reply::message(ctx.settings.message("sr.usage", &[]))));
+        }
+        let track_id = match parse_spotify_link(&query) {
+            Some(Ok(id)) => Some(id),
+            Some(Err(key)) => return Ok(Some(Reply::message(ctx.settings.message(key, &[])))),
+            None => None,
+        };
+
+        let settings = &ctx.settings.song_request;
+        if !settings.enabled {
+            return Ok(Some(Reply::message(
+                ctx.settings.message("sr.disabled", &[]),
+            )));
+        }
+        let is_limited = ctx.role < Role::Moderator;
+        if is_limited && !self.try_request(ctx.channel_name, ctx.display_name, settings) {
+            return Ok(Some(Reply::message(ctx.settings.message(
+                "sr.limit",
+                &[
+                    ("count", &settings.max_requests_per_user.to_string()),
+                    ("minutes", &(settings.request_window / 60).to_string()),
+                ],
+            ))));
+        }
+
+        let res = queue_request(ctx, &query, track_id.as_deref(), settings).await;
+        if is_limited && !matches!(res, Ok(ref r) if r.queued) {
+            self.undo_request(ctx.channel_name, ctx.display_name);
+        }
+        res.map(|r| Some(Reply::message(r.message)))
+    }
+}
+
+struct RequestResult {
+    queued: bool,
+    message: String,
+}
+
+async fn queue_request(
+    ctx: &CommandContext<'_>,
+    query: &str,
+    track_id: Option<&str>,
+    settings: &SongRequestSettings,
+) -> Result<RequestResult, String> {
+    let track = ctx
+        .web
+        .search_track(ctx.channel_name, query, track_id)
+        .await
+        .map_err(|e| format!("Could not search for track: {}", e))?;
+    let Some(track) = track else {
+        return Ok(RequestResult {
+            queued: false,
+            message: ctx.settings.message("sr.not_found", &[]),
+        });
+    };
+    if track.duration_ms / 1000 > settings.max_track_length {
+        return Ok(RequestResult {
+            queued: false,
+            message: ctx.settings.message(
+                "sr.too_long",
+                &[
+                    ("title", &track.name),
+                    ("minutes", &(settings.max_track_length / 60).to_string()),
+                ],
+            ),
+        });
+    }
+
+    let queued = ctx
+        .web
+        .queue_track(ctx.channel_name, &track.uri, ctx.display_name)
+        .await
+        .map_err(|e| format!("Could not queue track: {}", e))?;
+
+    Ok(RequestResult {
+        queued: true,
+        message: ctx.settings.message(
+            "sr.queued",
+            &[
+                ("song", &track.title()),
+                ("position", &queued.position.to_string()),
+            ],
+        ),
+    })
+}
+
+/// Extracts the track id from a Spotify link or URI, or the name of the message
+/// explaining why it can't be requested. Returns `None` if the query isn't a
+/// Spotify link at all, so it should be searched for instead.
+fn parse_spotify_link(query: &str) -> Option<Result<String, &'static str>> {
+    let path = if let Some(uri) = query.strip_prefix("spotify:") {
+        uri.split(':').collect::<Vec<&str>>()
+    } else {
+        let without_scheme = query
+            .strip_prefix("https://")
+            .or_else(|| query.strip_prefix("http://"))
+            .unwrap_or(query);
+        let path = without_scheme.strip_prefix("open.spotify.com/")?;
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        path.split('/')
+            .filter(|p| !p.is_empty() && !p.starts_with("intl-"))
+            .collect()
+    };
+
+    match path.as_slice() {
+        ["track", id] if is_spotify_id(id) => Some(Ok(id.to_string())),
+        ["track", ..] => Some(Err("sr.invalid_link")),
+        _ => Some(Err("sr.not_a_track")),
+    }
+}
+
+fn is_spotify_id(id: &str) -> bool {
+    id.len() == 22 && id.chars().all(|c| c.is_ascii_alphanumeric())
+}
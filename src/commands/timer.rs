@@ -0,0 +1,91 @@
+use super::{status_reply, BotCommand, CommandContext, Reply, Role};
+use async_trait::async_trait;
+
+const MAX_NAME_LENGTH: usize = 25;
+// A day, longer intervals would never post during a stream
+const MAX_INTERVAL_MINUTES: u64 = 1440;
+
+/// `?timer add <name> <minutes> <min chat lines> <message>` and
+/// `?timer remove <name>`.
+pub struct ManageTimers;
+
+#[async_trait]
+impl BotCommand for ManageTimers {
+    fn name(&self) -> &'static str {
+        "timer"
+    }
+
+    fn required_role(&self) -> Role {
+        Role::Moderator
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<Option<Reply>, String> {
+        let args: Vec<&str> = ctx.args.iter().copied().filter(|a| !a.is_empty()).collect();
+        match args.as_slice() {
+            ["add", name, minutes, min_lines, message @ ..] if !message.is_empty() => {
+                let name = parse_name(name);
+                let minutes = minutes
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|m| (1..=MAX_INTERVAL_MINUTES).contains(m));
+                let min_lines = min_lines.parse::<u64>().ok();
+                let (Some(name), Some(minutes), Some(min_lines)) = (name, minutes, min_lines)
+                else {
+                    return Ok(Some(usage(ctx)));
+                };
+                let res = ctx
+                    .web
+                    .save_timer(
+                        ctx.channel_name,
+                        &name,
+                        &message.join(" "),
+                        minutes * 60,
+                        min_lines,
+                    )
+                    .await;
+                ctx.registry.refresh_settings(ctx.channel_name).await;
+                status_reply(
+                    ctx,
+                    res,
+                    "Adding timer",
+                    ctx.settings.message(
+                        "timer.added",
+                        &[("name", &name), ("minutes", &minutes.to_string())],
+                    ),
+                )
+            }
+            ["remove", name] => {
+                let Some(name) = parse_name(name) else {
+                    return Ok(Some(usage(ctx)));
+                };
+                if !ctx.settings.timers.iter().any(|t| t.name == name) {
+                    return Ok(Some(Reply::message(
+                        ctx.settings.message("timer.missing", &[("name", &name)]),
+                    )));
+                }
+                let res = ctx.web.delete_timer(ctx.channel_name, &name).await;
+                ctx.registry.refresh_settings(ctx.channel_name).await;
+                status_reply(
+                    ctx,
+                    res,
+                    "Removing timer",
+                    ctx.settings.message("timer.removed", &[("name", &name)]),
+                )
+            }
+            _ => Ok(Some(usage(ctx))),
+        }
+    }
+}
+
+fn usage(ctx: &CommandContext<'_>) -> Reply {
+    Reply::message(ctx.settings.message("timer.usage", &[]))
+}
+
+fn parse_name(name: &str) -> Option<String> {
+    let name = name.to_lowercase();
+    let is_valid = name.len() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    is_valid.then_some(name)
+}
@@ -0,0 +1,243 @@
+use serde::Deserialize;
+use std::fmt;
+use std::io::ErrorKind;
+use std::str::FromStr;
+
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// Bot configuration, read once at startup from the TOML file at CONFIG_PATH
+/// (config.toml by default). Environment variables, also from .env, override
+/// the values in the file.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub twitch: TwitchConfig,
+    pub web: WebConfig,
+    pub amqp: AmqpConfig,
+    pub commands: CommandsConfig,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TwitchConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub refresh_token: String,
+    /// Must match the account the refresh token belongs to
+    pub bot_nick: String,
+    /// Channel to join on startup, in addition to the channels from stbot-web
+    pub channel_name: Option<String>,
+    /// Connect as justinfanNNNN and never send chat messages
+    pub anonymous: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebConfig {
+    pub uri: String,
+    /// Used to sign requests, must be the same secret configured in stbot-web
+    pub shared_secret: Option<String>,
+    /// Sent as a bearer token instead, if no shared secret is set
+    pub api_token: Option<String>,
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        WebConfig {
+            uri: "http://localhost:3000".to_string(),
+            shared_secret: None,
+            api_token: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct AmqpConfig {
+    /// Must be the same address used in stbot-web
+    pub addr: String,
+}
+
+impl Default for AmqpConfig {
+    fn default() -> Self {
+        AmqpConfig {
+            addr: "amqp://localhost:5672".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandsConfig {
+    /// Default seconds between uses of a command in a channel
+    pub global_cooldown: u64,
+    /// Default seconds between uses of a command by the same user
+    pub user_cooldown: u64,
+    /// Whether moderators and the broadcaster ignore cooldowns by default
+    pub cooldown_mod_bypass: bool,
+    /// Reply when a command is on cooldown instead of ignoring it
+    pub cooldown_reply: bool,
+    /// Milliseconds the current song is cached for
+    pub song_cache_ttl: u64,
+}
+
+impl Default for CommandsConfig {
+    fn default() -> Self {
+        CommandsConfig {
+            global_cooldown: 0,
+            user_cooldown: 0,
+            cooldown_mod_bypass: true,
+            cooldown_reply: false,
+            song_cache_ttl: 2000,
+        }
+    }
+}
+
+/// Every problem found in the configuration, so they can be fixed in one go.
+#[derive(Debug)]
+pub struct ConfigError(Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join("\n"))
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    pub fn load() -> Result<Config, ConfigError> {
+        let path = env_var("CONFIG_PATH").unwrap_or_else(|| DEFAULT_CONFIG_PATH.to_string());
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents)
+                .map_err(|e| ConfigError(vec![format!("Invalid {}: {}", path, e)]))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Config::default(),
+            Err(e) => return Err(ConfigError(vec![format!("Could not read {}: {}", path, e)])),
+        };
+
+        let mut errors = Vec::new();
+        config.apply_env(&mut errors);
+        config.validate(&mut errors);
+        if !errors.is_empty() {
+            return Err(ConfigError(errors));
+        }
+
+        Ok(config)
+    }
+
+    fn apply_env(&mut self, errors: &mut Vec<String>) {
+        let twitch = &mut self.twitch;
+        env_string("TWITCH_CLIENT_ID", &mut twitch.client_id);
+        env_string("TWITCH_CLIENT_SECRET", &mut twitch.client_secret);
+        env_string("TWITCH_REFRESH_TOKEN", &mut twitch.refresh_token);
+        env_string("TWITCH_BOT_NICK", &mut twitch.bot_nick);
+        env_option("TWITCH_CHANNEL_NAME", &mut twitch.channel_name);
+        env_flag("TWITCH_ANONYMOUS", &mut twitch.anonymous, errors);
+
+        env_string("WEB_URI", &mut self.web.uri);
+        env_option("WEB_SHARED_SECRET", &mut self.web.shared_secret);
+        env_option("WEB_API_TOKEN", &mut self.web.api_token);
+
+        env_string("AMQP_ADDR", &mut self.amqp.addr);
+
+        let commands = &mut self.commands;
+        env_parsed(
+            "COMMAND_GLOBAL_COOLDOWN",
+            &mut commands.global_cooldown,
+            errors,
+        );
+        env_parsed("COMMAND_USER_COOLDOWN", &mut commands.user_cooldown, errors);
+        env_flag(
+            "COMMAND_COOLDOWN_MOD_BYPASS",
+            &mut commands.cooldown_mod_bypass,
+            errors,
+        );
+        env_flag(
+            "COMMAND_COOLDOWN_REPLY",
+            &mut commands.cooldown_reply,
+            errors,
+        );
+        env_parsed("SONG_CACHE_TTL", &mut commands.song_cache_ttl, errors);
+    }
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        // Anonymous connections don't log in, so they need no credentials
+        if !self.twitch.anonymous {
+            let required = [
+                (
+                    "twitch.client_id",
+                    "TWITCH_CLIENT_ID",
+                    &self.twitch.client_id,
+                ),
+                (
+                    "twitch.client_secret",
+                    "TWITCH_CLIENT_SECRET",
+                    &self.twitch.client_secret,
+                ),
+                (
+                    "twitch.refresh_token",
+                    "TWITCH_REFRESH_TOKEN",
+                    &self.twitch.refresh_token,
+                ),
+                ("twitch.bot_nick", "TWITCH_BOT_NICK", &self.twitch.bot_nick),
+            ];
+            for (key, var, value) in required {
+                if value.is_empty() {
+                    errors.push(format!("Missing {} (or {})", key, var));
+                }
+            }
+        }
+        if let Err(e) = reqwest::Url::parse(&self.web.uri) {
+            errors.push(format!("Invalid web.uri \"{}\": {}", self.web.uri, e));
+        }
+        // An empty secret would sign every request with an empty key
+        if self.web.shared_secret.as_deref() == Some("") {
+            errors.push("Empty web.shared_secret, remove it or set a secret".to_string());
+        }
+        if self.web.api_token.as_deref() == Some("") {
+            errors.push("Empty web.api_token, remove it or set a token".to_string());
+        }
+        if self.amqp.addr.is_empty() {
+            errors.push("Missing amqp.addr (or AMQP_ADDR)".to_string());
+        }
+    }
+}
+
+/// Empty variables count as unset, so blank lines copied from `.env.example`
+/// don't override the config file.
+fn env_var(var: &str) -> Option<String> {
+    dotenv::var(var).ok().filter(|v| !v.is_empty())
+}
+
+fn env_string(var: &str, value: &mut String) {
+    if let Some(v) = env_var(var) {
+        *value = v;
+    }
+}
+
+fn env_option(var: &str, value: &mut Option<String>) {
+    if let Some(v) = env_var(var) {
+        *value = Some(v);
+    }
+}
+
+fn env_parsed<T: FromStr>(var: &str, value: &mut T, errors: &mut Vec<String>) {
+    let Some(v) = env_var(var) else {
+        return;
+    };
+    match v.parse() {
+        Ok(parsed) => *value = parsed,
+        Err(_) => errors.push(format!("Invalid {} \"{}\", expected a number", var, v)),
+    }
+}
+
+fn env_flag(var: &str, value: &mut bool, errors: &mut Vec<String>) {
+    let Some(v) = env_var(var) else {
+        return;
+    };
+    match v.to_lowercase().as_str() {
+        "1" | "true" => *value = true,
+        "0" | "false" => *value = false,
+        _ => errors.push(format!("Invalid {} \"{}\", expected true or false", var, v)),
+    }
+}
@@ -1,35 +1,108 @@
+mod channel_state;
+mod circuit_breaker;
+mod commands;
+mod config;
 mod message_parser;
+mod messages;
+mod notice;
+mod now_playing;
+mod outbox;
+mod settings;
+mod song_cache;
+mod song_history;
+mod timers;
+mod vote_skip;
+mod web;
+use channel_state::ChannelStates;
+use commands::{CommandRegistry, Role};
+use config::{Config, TwitchConfig};
 use futures::stream::{SplitSink, SplitStream};
 use futures::{SinkExt, StreamExt};
 use lapin::Channel;
 use lapin::{options::*, types::FieldTable, Connection, ConnectionProperties};
 use message_parser::{parse_message, MessageResponse};
+use notice::Notice;
+use outbox::{alter_duplicate, Outbox};
 use reqwest::header::CONTENT_TYPE;
-use reqwest::StatusCode;
 use serde::Deserialize;
+use settings::Settings;
+use song_cache::SongCache;
+use song_history::SongHistory;
 use std::collections::HashMap;
 use std::str;
-use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 use tokio::net::TcpStream;
+use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::mpsc::{self, Receiver, Sender};
-use tokio::task::JoinHandle;
-use tokio::time::{sleep, Duration};
+use tokio::sync::Semaphore;
+use tokio::time::{sleep, sleep_until, Duration};
 use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use vote_skip::VoteSkip;
+use web::WebClient;
 
 const MAX_CONNECTION_ATTEMPTS: i32 = 12;
+const MAX_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(60);
+// Chat messages handled at the same time across all channels
+const MAX_CONCURRENT_MESSAGES: usize = 8;
+// Chat messages waiting to be handled per channel before new ones are dropped
+const CHANNEL_QUEUE_SIZE: usize = 32;
 
 #[tokio::main]
 async fn main() {
-    let amqp_addr = dotenv::var("AMQP_ADDR").unwrap_or_else(|_| "amqp://localhost:5672".into());
-    let amqp_conn = Connection::connect(&amqp_addr, ConnectionProperties::default())
+    let config = match Config::load() {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            eprintln!("[ERROR] Invalid configuration:\n{}", e);
+            return;
+        }
+    };
+    let web = match WebClient::from_config(&config.web) {
+        Ok(web) => Arc::new(web),
+        Err(e) => {
+            eprintln!("[ERROR] Could not create stbot-web client: {}", e);
+            return;
+        }
+    };
+    tokio::spawn(web::start_metrics_logger(web.clone()));
+    let amqp_conn = Connection::connect(&config.amqp.addr, ConnectionProperties::default())
         .await
         .unwrap();
     let channel = amqp_conn.create_channel().await.unwrap();
+    let settings = Arc::new(Settings::default());
+    tokio::spawn(settings::start_settings_refresh(
+        settings.clone(),
+        web.clone(),
+    ));
+    tokio::spawn(settings::start_settings_consumer(
+        channel.clone(),
+        settings.clone(),
+        web.clone(),
+    ));
+    let anonymous = config.twitch.anonymous;
+    if anonymous {
+        println!("[INFO] Running in anonymous read-only mode, outbound messages are disabled");
+    }
 
     let mut connection_attempts = 0;
     let mut reconnect_delay = 0;
+    let mut login_failures = 0;
+    let song_history = Arc::new(SongHistory::default());
+    let songs = Arc::new(SongCache::new(
+        Duration::from_millis(config.commands.song_cache_ttl),
+        song_history.clone(),
+    ));
+    // Created once, so vote skips and cooldowns carry over reconnects
+    let vote_skip = Arc::new(VoteSkip::default());
+    let registry = Arc::new(CommandRegistry::new(
+        &config,
+        vote_skip.clone(),
+        song_history,
+        songs.clone(),
+        settings.clone(),
+        web.clone(),
+    ));
 
     while connection_attempts < MAX_CONNECTION_ATTEMPTS {
         sleep(Duration::from_secs(reconnect_delay)).await;
@@ -55,22 +128,77 @@ async fn main() {
         let (ws_stream, _) = ws_connection_result.unwrap();
         let (ws_tx, ws_rx) = ws_stream.split();
         let consumer_th = tokio::spawn(start_consumer(channel.clone(), tx.clone()));
-
-        let (ws_rx, ws_tx) = tokio::join!(start_ws(tx.clone(), ws_rx), start_reader(rx, ws_tx));
+        let channel_states = ChannelStates::default();
+        // Anonymous connections never post to chat, so they don't announce songs or run timers
+        let posting_ths = if anonymous {
+            Vec::new()
+        } else {
+            vec![
+                tokio::spawn(now_playing::start_now_playing(
+                    tx.clone(),
+                    channel_states.clone(),
+                    songs.clone(),
+                    settings.clone(),
+                    web.clone(),
+                )),
+                tokio::spawn(timers::start_timers(
+                    tx.clone(),
+                    channel_states.clone(),
+                    settings.clone(),
+                    web.clone(),
+                )),
+            ]
+        };
+
+        let ((ws_rx, ws_exit), ws_tx) = tokio::join!(
+            start_ws(
+                tx.clone(),
+                ws_rx,
+                config.clone(),
+                channel_states.clone(),
+                vote_skip.clone(),
+                registry.clone(),
+                web.clone()
+            ),
+            start_reader(rx, ws_tx, anonymous, channel_states)
+        );
 
         println!("[INFO] Aborting consumer thread...");
         consumer_th.abort();
+        for th in posting_ths {
+            th.abort();
+        }
 
         println!("[INFO] Closing websocket connection...");
         if let Err(e) = ws_tx.reunite(ws_rx).unwrap().close(None).await {
             println!("[ERROR] Could not close websocket connection: {:?}", e)
         }
+
+        if let WsExit::LoginFailed = ws_exit {
+            login_failures += 1;
+            if login_failures >= MAX_CONNECTION_ATTEMPTS {
+                eprintln!("[ERROR] Giving up after {} failed logins", login_failures);
+                return;
+            }
+            sleep(Duration::from_secs(1 << login_failures.min(6))).await;
+        } else {
+            login_failures = 0;
+        }
     }
 }
 
+enum WsExit {
+    Closed,
+    LoginFailed,
+}
+
 enum ReaderActionEvent {
     Message,
     Close,
+    /// Pause sending after Twitch reported that we hit the rate limit
+    Backoff,
+    /// Resend the last message to the given channel, altered to not be a duplicate
+    Resend,
 }
 
 struct ReaderAction {
@@ -81,22 +209,118 @@ struct ReaderAction {
 async fn start_reader(
     mut rx: Receiver<ReaderAction>,
     mut ws_tx: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
+    anonymous: bool,
+    channel_states: ChannelStates,
 ) -> SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message> {
-    while let Some(reader_act) = rx.recv().await {
-        match reader_act.event {
-            ReaderActionEvent::Message => {
-                let msg = reader_act.message.unwrap();
-                println!("[INFO] Response: {}", msg);
-                ws_tx.send(msg.into()).await.unwrap();
+    let mut outbox = Outbox::default();
+    // When chat messages held back in the outbox can be sent
+    let mut next_send: Option<Instant> = None;
+    let mut last_rate_limit: Option<Instant> = None;
+    let mut backoff = Duration::ZERO;
+
+    loop {
+        let reader_act = tokio::select! {
+            reader_act = rx.recv() => match reader_act {
+                Some(reader_act) => Some(reader_act),
+                None => break,
+            },
+            _ = sleep_until(next_send.unwrap_or_else(Instant::now).into()), if next_send.is_some() => None,
+        };
+
+        match reader_act.map(|a| (a.event, a.message)) {
+            None => {}
+            Some((ReaderActionEvent::Message, msg)) => {
+                let msg = msg.unwrap();
+                if anonymous && is_privmsg(&msg) {
+                    println!("[INFO] Anonymous mode, not sending: {}", msg);
+                    continue;
+                }
+                // Chat messages wait for slow mode and rate limit backoffs in the
+                // outbox, anything else like PONG, JOIN and PART goes out right away
+                match privmsg_channel(&msg).map(|c| c.to_string()) {
+                    Some(channel) => outbox.push(&channel, msg),
+                    None => {
+                        println!("[INFO] Response: {}", msg);
+                        ws_tx.send(msg.into()).await.unwrap();
+                        continue;
+                    }
+                }
             }
-            ReaderActionEvent::Close => {
+            Some((ReaderActionEvent::Close, _)) => {
                 println!("[INFO] Closing reader");
                 return ws_tx;
             }
+            Some((ReaderActionEvent::Backoff, _)) => {
+                let recently_limited = last_rate_limit
+                    .map(|l| l.elapsed() < MAX_RATE_LIMIT_BACKOFF)
+                    .unwrap_or(false);
+                backoff = if recently_limited {
+                    (backoff * 2).min(MAX_RATE_LIMIT_BACKOFF)
+                } else {
+                    Duration::from_secs(2)
+                };
+                println!("[INFO] Rate limited, pausing messages for {:?}", backoff);
+                last_rate_limit = Some(Instant::now());
+                outbox.pause_until(Instant::now() + backoff);
+            }
+            Some((ReaderActionEvent::Resend, channel)) => {
+                let channel = channel.unwrap();
+                let msg = {
+                    let mut channel_states = channel_states.lock().unwrap();
+                    let Some(state) = channel_states.get_mut(&channel) else {
+                        continue;
+                    };
+                    let Some((sent_at, msg)) = state.last_sent.take() else {
+                        continue;
+                    };
+                    // Twitch only rejects duplicates sent within 30 seconds
+                    if sent_at.elapsed() > Duration::from_secs(30) {
+                        continue;
+                    }
+                    let msg = alter_duplicate(&msg);
+                    state.last_sent = Some((Instant::now(), msg.clone()));
+                    msg
+                };
+                println!("[INFO] Resending duplicate message: {}", msg);
+                ws_tx.send(msg.into()).await.unwrap();
+            }
+        }
+
+        let (ready, next) = outbox.take_ready(&channel_states, Instant::now());
+        next_send = next;
+        for msg in ready {
+            println!("[INFO] Response: {}", msg);
+            ws_tx.send(msg.into()).await.unwrap();
         }
     }
 
-    return ws_tx;
+    ws_tx
+}
+
+/// Twitch accepts any `justinfan` nick without a password for read-only connections.
+fn anonymous_nick() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    format!("justinfan{}", 10000 + nanos % 90000)
+}
+
+/// Checks if an outbound IRC line is a PRIVMSG, ignoring any leading tags.
+fn is_privmsg(irc_message: &str) -> bool {
+    privmsg_channel(irc_message).is_some()
+}
+
+/// Returns the channel (with a # in front) an outbound PRIVMSG is sent to.
+fn privmsg_channel(irc_message: &str) -> Option<&str> {
+    let command = match irc_message.strip_prefix('@') {
+        Some(rest) => rest.split_once(' ').map(|(_, c)| c).unwrap_or_default(),
+        None => irc_message,
+    };
+    command
+        .trim_start()
+        .strip_prefix("PRIVMSG ")
+        .and_then(|rest| rest.split(' ').next())
 }
 
 async fn start_consumer(channel: Channel, tx: Sender<ReaderAction>) {
@@ -125,28 +349,40 @@ async fn start_consumer(channel: Channel, tx: Sender<ReaderAction>) {
 async fn start_ws(
     tx: Sender<ReaderAction>,
     mut ws_rx: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
-) -> SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+    config: Arc<Config>,
+    channel_states: ChannelStates,
+    vote_skip: Arc<VoteSkip>,
+    registry: Arc<CommandRegistry>,
+    web: Arc<WebClient>,
+) -> (
+    SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
+    WsExit,
+) {
     tx.send(ReaderAction {
         event: ReaderActionEvent::Message,
         message: Some("CAP REQ :twitch.tv/tags twitch.tv/commands".into()),
     })
     .await
     .unwrap();
-    let access_token = get_twitch_access_token().await.unwrap();
-    tx.send(ReaderAction {
-        event: ReaderActionEvent::Message,
-        message: Some(format!("PASS oauth:{}", access_token).into()),
-    })
-    .await
-    .unwrap();
+    let nick = if config.twitch.anonymous {
+        anonymous_nick()
+    } else {
+        let access_token = get_twitch_access_token(&config.twitch).await.unwrap();
+        tx.send(ReaderAction {
+            event: ReaderActionEvent::Message,
+            message: Some(format!("PASS oauth:{}", access_token).into()),
+        })
+        .await
+        .unwrap();
+        config.twitch.bot_nick.clone()
+    };
     tx.send(ReaderAction {
         event: ReaderActionEvent::Message,
-        message: Some(format!("NICK {}", dotenv::var("TWITCH_BOT_NICK").unwrap()).into()),
+        message: Some(format!("NICK {}", nick).into()),
     })
     .await
     .unwrap();
-    let twitch_ch_name = dotenv::var("TWITCH_CHANNEL_NAME");
-    if let Ok(ch) = twitch_ch_name {
+    if let Some(ch) = &config.twitch.channel_name {
         tx.send(ReaderAction {
             event: ReaderActionEvent::Message,
             message: Some(format!("JOIN #{}", ch).into()),
@@ -154,7 +390,23 @@ async fn start_ws(
         .await
         .unwrap();
     }
-    let mut active_users = get_active_users().await.unwrap().users;
+    let mut active_users = web.active_users().await.unwrap_or_else(|e| {
+        eprintln!("[ERROR] Could not get channels to join: {}", e);
+        Vec::new()
+    });
+    let joined: Vec<String> = config
+        .twitch
+        .channel_name
+        .iter()
+        .chain(active_users.iter())
+        .cloned()
+        .collect();
+    let prefetch_registry = registry.clone();
+    tokio::spawn(async move {
+        for channel_name in joined {
+            prefetch_registry.refresh_settings(&channel_name).await;
+        }
+    });
     for username in active_users.iter_mut() {
         *username = format!("#{}", username)
     }
@@ -166,9 +418,9 @@ async fn start_ws(
         .await
         .unwrap();
     }
-    let mut skip_channels = HashMap::<String, Arc<Mutex<Vec<String>>>>::new();
-    let mut handles: Vec<JoinHandle<()>> = Vec::new();
-    let mut last_skip = SystemTime::now();
+    let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_MESSAGES));
+    // Keyed by channel name with a # in front
+    let mut workers: HashMap<String, Sender<(String, MessageResponse)>> = HashMap::new();
 
     while let Some(result) = ws_rx.next().await {
         match result {
@@ -178,7 +430,52 @@ async fn start_ws(
                     let messages: Vec<&str> = message_str.trim_end().split("\r\n").collect();
                     for m in messages.iter() {
                         let parsed_message = parse_message(m);
-                        let response = generate_response(parsed_message).await;
+                        channel_state::update(&channel_states, &parsed_message);
+                        vote_skip
+                            .active_chatters
+                            .lock()
+                            .unwrap()
+                            .record(&parsed_message);
+                        let worker_channel = parsed_message
+                            .command
+                            .as_ref()
+                            .filter(|c| c.command.as_deref() == Some("PRIVMSG"))
+                            .and_then(|c| c.channel.clone());
+                        if let Some(channel) = worker_channel {
+                            // Anonymous connections only observe chat, commands would
+                            // change playback on behalf of the real bot
+                            if config.twitch.anonymous {
+                                continue;
+                            }
+                            let worker = workers.entry(channel.clone()).or_insert_with(|| {
+                                spawn_channel_worker(registry.clone(), permits.clone(), tx.clone())
+                            });
+                            match worker.try_send((m.to_string(), parsed_message)) {
+                                Ok(()) => {}
+                                Err(TrySendError::Full(_)) => {
+                                    eprintln!(
+                                        "[ERROR] Too many messages queued in {}, dropping: {}",
+                                        channel, m
+                                    );
+                                }
+                                Err(TrySendError::Closed(message)) => {
+                                    eprintln!(
+                                        "[ERROR] Message worker for {} stopped, restarting it",
+                                        channel
+                                    );
+                                    let worker = spawn_channel_worker(
+                                        registry.clone(),
+                                        permits.clone(),
+                                        tx.clone(),
+                                    );
+                                    // A new worker's queue is empty, so this can't fail
+                                    let _ = worker.try_send(message);
+                                    workers.insert(channel, worker);
+                                }
+                            }
+                            continue;
+                        }
+                        let response = generate_response(parsed_message, &registry).await;
                         match response {
                             Ok(gr) => {
                                 if let Some(r) = gr {
@@ -202,22 +499,29 @@ async fn start_ws(
                                             })
                                             .await
                                             .unwrap();
-                                            return ws_rx;
+                                            return (ws_rx, WsExit::Closed);
                                         }
-                                        ResponseEvent::Skip => {
-                                            let min_secs_between_skips = Duration::from_secs(10);
-                                            if last_skip.elapsed().unwrap()
-                                                >= min_secs_between_skips
-                                            {
-                                                handle_skip(
-                                                    &mut skip_channels,
-                                                    &mut handles,
-                                                    &mut last_skip,
-                                                    r,
-                                                    &tx,
-                                                )
-                                                .await;
+                                        ResponseEvent::Notice => {
+                                            let notice = r.notice.unwrap();
+                                            if notice == Notice::LoginFailed {
+                                                eprintln!("[ERROR] Login failed: {}", m);
+                                                tx.send(ReaderAction {
+                                                    event: ReaderActionEvent::Close,
+                                                    message: None,
+                                                })
+                                                .await
+                                                .unwrap();
+                                                return (ws_rx, WsExit::LoginFailed);
                                             }
+                                            handle_notice(
+                                                notice,
+                                                r.channel_name,
+                                                r.message,
+                                                &tx,
+                                                &channel_states,
+                                                &web,
+                                            )
+                                            .await;
                                         }
                                     };
                                 }
@@ -235,12 +539,105 @@ async fn start_ws(
                 })
                 .await
                 .unwrap();
-                return ws_rx;
+                return (ws_rx, WsExit::Closed);
             }
         }
     }
 
-    return ws_rx;
+    return (ws_rx, WsExit::Closed);
+}
+
+/// Handles a channel's chat messages one at a time so replies keep their order,
+/// while messages in other channels are handled concurrently.
+fn spawn_channel_worker(
+    registry: Arc<CommandRegistry>,
+    permits: Arc<Semaphore>,
+    tx: Sender<ReaderAction>,
+) -> Sender<(String, MessageResponse)> {
+    let (worker_tx, mut worker_rx) = mpsc::channel::<(String, MessageResponse)>(CHANNEL_QUEUE_SIZE);
+    tokio::spawn(async move {
+        while let Some((m, parsed_message)) = worker_rx.recv().await {
+            let Ok(_permit) = permits.acquire().await else {
+                return;
+            };
+            match generate_response(parsed_message, &registry).await {
+                Ok(Some(GeneratedResponse {
+                    event: ResponseEvent::Message,
+                    message: Some(response_message),
+                    ..
+                })) => {
+                    println!("[INFO] Message: {}", m);
+                    tx.send(ReaderAction {
+                        event: ReaderActionEvent::Message,
+                        message: Some(response_message),
+                    })
+                    .await
+                    .unwrap_or_else(|e| eprintln!("[ERROR] Sender Error: {:?}", e));
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("[ERROR] {}", e),
+            }
+        }
+    });
+
+    worker_tx
+}
+
+async fn handle_notice(
+    notice: Notice,
+    channel_name: Option<String>,
+    message: Option<String>,
+    tx: &Sender<ReaderAction>,
+    channel_states: &ChannelStates,
+    web: &Arc<WebClient>,
+) {
+    let channel_name = channel_name.unwrap_or_default();
+    let message = message.unwrap_or_default();
+    println!(
+        "[INFO] Notice {} in {}: {}",
+        notice.msg_id(),
+        channel_name,
+        message
+    );
+
+    let action = match notice {
+        Notice::RateLimited => Some(ReaderAction {
+            event: ReaderActionEvent::Backoff,
+            message: None,
+        }),
+        Notice::Duplicate => Some(ReaderAction {
+            event: ReaderActionEvent::Resend,
+            message: Some(channel_name.clone()),
+        }),
+        ref n if n.should_part() => {
+            // Stops announcements and timers from posting to the channel
+            channel_states.lock().unwrap().remove(&channel_name);
+            Some(ReaderAction {
+                event: ReaderActionEvent::Message,
+                message: Some(format!("PART {}", channel_name)),
+            })
+        }
+        _ => None,
+    };
+    if let Some(a) = action {
+        tx.send(a)
+            .await
+            .unwrap_or_else(|e| eprintln!("[ERROR] Sender Error: {:?}", e));
+    }
+
+    if let Notice::Other(_) = notice {
+        return;
+    }
+    let web = web.clone();
+    tokio::spawn(async move {
+        let channel_name = channel_name.trim_start_matches('#');
+        if let Err(e) = web
+            .report_notice(channel_name, notice.msg_id(), &message)
+            .await
+        {
+            eprintln!("[ERROR] Could not report notice: {}", e);
+        }
+    });
 }
 
 #[derive(Deserialize, Debug)]
@@ -249,18 +646,14 @@ struct TwitchTokenResponse {
     access_token: String,
 }
 
-async fn get_twitch_access_token() -> Result<String, Box<dyn std::error::Error>> {
+async fn get_twitch_access_token(
+    config: &TwitchConfig,
+) -> Result<String, Box<dyn std::error::Error>> {
     let params = [
         ("grant_type", "refresh_token"),
-        ("client_id", &dotenv::var("TWITCH_CLIENT_ID").unwrap()),
-        (
-            "client_secret",
-            &dotenv::var("TWITCH_CLIENT_SECRET").unwrap(),
-        ),
-        (
-            "refresh_token",
-            &dotenv::var("TWITCH_REFRESH_TOKEN").unwrap(),
-        ),
+        ("client_id", &config.client_id),
+        ("client_secret", &config.client_secret),
+        ("refresh_token", &config.refresh_token),
     ];
     let client = reqwest::Client::new();
     let res = client
@@ -275,107 +668,18 @@ async fn get_twitch_access_token() -> Result<String, Box<dyn std::error::Error>>
     return Ok(res.access_token);
 }
 
-async fn handle_skip(
-    skip_channels: &mut HashMap<String, Arc<Mutex<Vec<String>>>>,
-    handles: &mut Vec<JoinHandle<()>>,
-    last_skip: &mut SystemTime,
-    gr: GeneratedResponse,
-    tx: &Sender<ReaderAction>,
-) {
-    let channel_name = gr.channel_name.unwrap();
-    if let None = skip_channels.get(&channel_name) {
-        skip_channels.insert(channel_name.clone(), Arc::new(Mutex::new(Vec::new())));
-    }
-    let current_skip_users = skip_channels.get_mut(&channel_name).unwrap();
-    let username = gr.username.unwrap();
-    if !current_skip_users.lock().unwrap().contains(&username) {
-        current_skip_users.lock().unwrap().push(username.clone());
-        // TODO: change required votes to 5 or sum
-        let required_skips = 5;
-        if current_skip_users.lock().unwrap().len() >= required_skips {
-            let res = skip_current_song(&channel_name.clone()).await;
-            match res {
-                Ok(s) => {
-                    if s.is_success() {
-                        tx.send(ReaderAction {
-                            event: ReaderActionEvent::Message,
-                            message: Some(format!(
-                                "PRIVMSG {} :Vote skip passed",
-                                channel_name.clone()
-                            )),
-                        })
-                        .await
-                        .unwrap_or_else(|e| eprintln!("[ERROR] Sender Error: {:?}", e));
-                        *last_skip = SystemTime::now();
-                        for h in handles {
-                            h.abort();
-                        }
-                        current_skip_users.lock().unwrap().clear();
-                    } else {
-                        println!("[INFO] Could not skip song, status code: {}", s);
-                    }
-                }
-                Err(e) => {
-                    eprintln!("[ERROR] Error skipping song: {:?}", e);
-                }
-            }
-        } else {
-            let current_skip_users = Arc::clone(&current_skip_users);
-            let username = username.clone();
-            let handle = tokio::spawn(async move {
-                sleep(Duration::from_secs(30)).await;
-                let i = current_skip_users
-                    .lock()
-                    .unwrap()
-                    .iter()
-                    .position(|n| *n == username)
-                    .unwrap();
-                current_skip_users.lock().unwrap().remove(i);
-            });
-            handles.push(handle);
-        }
-    }
-}
-
-async fn skip_current_song(channel_name: &str) -> Result<StatusCode, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-    let url = dotenv::var("WEB_URI").unwrap() + "/api/spotify/skip";
-    let params = [("channel_name", channel_name)];
-    let url = reqwest::Url::parse_with_params(url.as_str(), &params)?;
-    let status = client.post(url).send().await?.status();
-
-    return Ok(status);
-}
-
-#[derive(Deserialize, Debug)]
-struct ActiveUsersResponse {
-    users: Vec<String>,
-}
-
-async fn get_active_users() -> Result<ActiveUsersResponse, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-    let res = client
-        .get(dotenv::var("WEB_URI").unwrap() + "/api/active")
-        .send()
-        .await?
-        .json::<ActiveUsersResponse>()
-        .await?;
-
-    return Ok(res);
-}
-
 #[derive(Default)]
 struct GeneratedResponse {
     event: ResponseEvent,
     message: Option<String>,
-    username: Option<String>,
     channel_name: Option<String>,
+    notice: Option<Notice>,
 }
 
 enum ResponseEvent {
     Reconnect,
     Message,
-    Skip,
+    Notice,
 }
 
 impl Default for ResponseEvent {
@@ -386,6 +690,7 @@ impl Default for ResponseEvent {
 
 async fn generate_response(
     parsed_message: MessageResponse,
+    registry: &CommandRegistry,
 ) -> Result<Option<GeneratedResponse>, String> {
     let command = parsed_message
         .command
@@ -399,6 +704,7 @@ async fn generate_response(
         .and_then(|c| c.channel)
         .unwrap_or_default();
     let message = parsed_message.parameters.unwrap_or_default();
+    let badges = parsed_message.tags.as_ref().and_then(|t| t.badges.clone());
     let tags = parsed_message.tags.and_then(|t| t.other);
     let message_id = tags
         .clone()
@@ -415,6 +721,11 @@ async fn generate_response(
         })
         .unwrap_or_default();
     let is_channel_owner = format!("#{}", display_name.to_lowercase()) == channel.to_lowercase();
+    let role = if is_channel_owner {
+        Role::Broadcaster
+    } else {
+        Role::from_badges(badges.as_ref())
+    };
 
     match command.as_str() {
         "PING" => {
@@ -425,30 +736,35 @@ async fn generate_response(
             }));
         }
         "PRIVMSG" => {
-            let reply = reply_message(message.as_str(), &channel[1..], &is_channel_owner).await?;
+            let reply = registry
+                .dispatch(message.as_str(), &channel[1..], &display_name, role)
+                .await?;
             if let Some(r) = reply {
-                return match r.reply_type {
-                    ReplyType::Message => Ok(Some(GeneratedResponse {
-                        event: ResponseEvent::Message,
-                        message: Some(format!(
-                            "@reply-parent-msg-id={} PRIVMSG {} :{}",
-                            message_id,
-                            channel,
-                            r.message.unwrap()
-                        )),
-                        ..Default::default()
-                    })),
-                    ReplyType::Skip => Ok(Some(GeneratedResponse {
-                        event: ResponseEvent::Skip,
-                        username: Some(display_name),
-                        message: None,
-                        channel_name: Some(channel),
-                    })),
-                };
+                return Ok(Some(GeneratedResponse {
+                    event: ResponseEvent::Message,
+                    message: Some(format!(
+                        "@reply-parent-msg-id={} PRIVMSG {} :{}",
+                        message_id, channel, r.message
+                    )),
+                    ..Default::default()
+                }));
             } else {
                 return Ok(None);
             }
         }
+        "NOTICE" => {
+            let msg_id = tags
+                .as_ref()
+                .and_then(|o| o.get("msg-id"))
+                .map(|s| s.as_str());
+            return Ok(Some(GeneratedResponse {
+                event: ResponseEvent::Notice,
+                notice: Some(Notice::parse(msg_id, &message)),
+                message: Some(message),
+                channel_name: Some(channel),
+                ..Default::default()
+            }));
+        }
         "RECONNECT" => {
             return Ok(Some(GeneratedResponse {
                 event: ResponseEvent::Reconnect,
@@ -458,193 +774,3 @@ async fn generate_response(
         _ => Ok(None),
     }
 }
-
-#[derive(Default)]
-struct Reply {
-    message: Option<String>,
-    reply_type: ReplyType,
-}
-
-enum ReplyType {
-    Message,
-    Skip,
-}
-
-impl Default for ReplyType {
-    fn default() -> Self {
-        ReplyType::Message
-    }
-}
-
-async fn reply_message(
-    user_msg: &str,
-    channel_name: &str,
-    is_channel_owner: &bool,
-) -> Result<Option<Reply>, String> {
-    let tokens: Vec<&str> = user_msg.split(" ").collect();
-    match tokens.get(0) {
-        Some(command) => match command.to_owned() {
-            "?song" => {
-                let song = get_spotify_song(channel_name).await;
-                match song {
-                    Ok(s) => {
-                        if s.is_playing {
-                            return Ok(Some(Reply {
-                                message: Some(format!(
-                                    "{} - {}",
-                                    s.item.artists[0].name, s.item.name
-                                )),
-                                ..Default::default()
-                            }));
-                        }
-                        return Ok(Some(Reply {
-                            message: Some("No song currently playing".to_string()),
-                            ..Default::default()
-                        }));
-                    }
-                    Err(e) => {
-                        return Err(format!("Could not get song: {:?}", e));
-                    }
-                };
-            }
-            "?slink" | "?songlink" => {
-                let song = get_spotify_song(channel_name).await;
-                match song {
-                    Ok(s) => {
-                        if s.is_playing {
-                            return Ok(Some(Reply {
-                                message: Some(s.item.external_urls.spotify),
-                                ..Default::default()
-                            }));
-                        }
-                        return Ok(Some(Reply {
-                            message: Some("No song currently playing".to_string()),
-                            ..Default::default()
-                        }));
-                    }
-                    Err(e) => {
-                        return Err(format!("Could not get song: {:?}", e));
-                    }
-                };
-            }
-            "?skip" => {
-                return Ok(Some(Reply {
-                    reply_type: ReplyType::Skip,
-                    ..Default::default()
-                }));
-            }
-            "?skipon" => {
-                if !is_channel_owner {
-                    return Err("User is not the channel owner.".into());
-                };
-                let res = enable_song_skip(&channel_name).await;
-                match res {
-                    Ok(s) => {
-                        if s.is_success() {
-                            return Ok(Some(Reply {
-                                reply_type: ReplyType::Message,
-                                message: Some("Vote skip is now enabled".into()),
-                                ..Default::default()
-                            }));
-                        }
-
-                        return Err(format!(
-                            "Enabling song skip failed with status code {}",
-                            s.to_string()
-                        ));
-                    }
-                    Err(e) => Err(format!("Enabling song skip failed: {:?}", e)),
-                }
-            }
-            "?skipoff" => {
-                if !is_channel_owner {
-                    return Err("User is not the channel owner.".into());
-                };
-                let res = disable_song_skip(&channel_name).await;
-                match res {
-                    Ok(s) => {
-                        if s.is_success() {
-                            return Ok(Some(Reply {
-                                reply_type: ReplyType::Message,
-                                message: Some("Vote skip is now disabled".into()),
-                                ..Default::default()
-                            }));
-                        }
-
-                        return Err(format!(
-                            "Disabling song skip failed with status code {}",
-                            s.to_string()
-                        ));
-                    }
-                    Err(e) => Err(format!("Disabling song skip failed: {:?}", e)),
-                }
-            }
-            "?commands" => {
-                return Ok(Some(Reply {
-                    reply_type: ReplyType::Message,
-                    message: Some("?song ?songlink ?slink ?skip ?skipon ?skipoff".into()),
-                    ..Default::default()
-                }));
-            }
-            _ => Ok(None),
-        },
-        None => Err("No token found.".into()),
-    }
-}
-
-async fn enable_song_skip(
-    channel_name: &str,
-) -> Result<reqwest::StatusCode, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-    let url = dotenv::var("WEB_URI").unwrap() + "/api/commands/skip/add";
-    let params = [("channel_name", channel_name)];
-    let url = reqwest::Url::parse_with_params(url.as_str(), &params)?;
-    let status = client.post(url).send().await?.status();
-
-    return Ok(status);
-}
-
-async fn disable_song_skip(
-    channel_name: &str,
-) -> Result<reqwest::StatusCode, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-    let url = dotenv::var("WEB_URI").unwrap() + "/api/commands/skip/remove";
-    let params = [("channel_name", channel_name)];
-    let url = reqwest::Url::parse_with_params(url.as_str(), &params)?;
-    let status = client.post(url).send().await?.status();
-
-    return Ok(status);
-}
-
-#[derive(Deserialize, Debug)]
-struct SongResponse {
-    is_playing: bool,
-    item: Item,
-}
-
-#[derive(Deserialize, Debug)]
-struct Item {
-    name: String,
-    external_urls: ExternalUrls,
-    artists: Vec<Artist>,
-}
-
-#[derive(Deserialize, Debug)]
-struct ExternalUrls {
-    spotify: String,
-}
-
-#[derive(Deserialize, Debug)]
-struct Artist {
-    name: String,
-}
-
-async fn get_spotify_song(channel_name: &str) -> Result<SongResponse, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-    let url = dotenv::var("WEB_URI").unwrap() + "/api/spotify/song";
-    let params = [("channel_name", channel_name)];
-    let url = reqwest::Url::parse_with_params(url.as_str(), &params)?;
-    let res = client.get(url).send().await?.json::<SongResponse>().await?;
-
-    return Ok(res);
-}
@@ -10,7 +10,7 @@ pub struct MessageResponse {
 
 #[derive(Default, Debug, Clone)]
 pub struct Tags {
-    badges: Option<HashMap<String, String>>,
+    pub badges: Option<HashMap<String, String>>,
     emote_sets: Option<HashMap<String, String>>,
     emotes: Option<HashMap<String, Vec<Emote>>>,
     pub other: Option<HashMap<String, String>>,
@@ -0,0 +1,250 @@
+use serde::Deserialize;
+
+/// Language of the bot's replies in a channel.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Sv,
+}
+
+// Every message accepts {prefix}, the channel's command prefix
+const EN: &[(&str, &str)] = &[
+    ("cooldown", "{prefix}{command} is on cooldown ({seconds}s)"),
+    (
+        "unavailable",
+        "Spotify commands are unavailable right now, try again in a bit",
+    ),
+    (
+        "request.failed",
+        "That didn't work, the request failed with status code {status}",
+    ),
+    ("now_playing", "Now playing: {song}"),
+    ("song.not_playing", "No song currently playing"),
+    ("songlink.local", "{title} is a local file and has no link"),
+    ("skip.vote_cooldown", "Vote skip opens again in {seconds}s"),
+    ("skip.votes", "{votes}/{required} votes to skip {title}"),
+    ("skip.vote_passed", "Vote skip passed"),
+    ("skip.skipped", "Song skipped"),
+    ("skip.no_vote", "No vote skip in progress"),
+    ("skip.vetoed", "Vote skip cancelled"),
+    ("skip.status", "{votes}/{required} votes to skip"),
+    (
+        "skipon.usage",
+        "Usage: {prefix}skipon [votes or percentage%] [vote lifetime secs] [cooldown secs]",
+    ),
+    ("skipon.enabled", "Vote skip is now enabled"),
+    ("skipoff.disabled", "Vote skip is now disabled"),
+    (
+        "sr.usage",
+        "Usage: {prefix}sr <song name or Spotify track link>",
+    ),
+    ("sr.invalid_link", "Invalid Spotify track link"),
+    (
+        "sr.not_a_track",
+        "Only Spotify track links can be requested",
+    ),
+    ("sr.disabled", "Song requests are disabled"),
+    (
+        "sr.limit",
+        "You can only request {count} songs every {minutes} minutes",
+    ),
+    ("sr.not_found", "No track found"),
+    (
+        "sr.too_long",
+        "{title} is too long, the max length is {minutes} minutes",
+    ),
+    ("sr.queued", "Queued {song} at position {position}"),
+    ("pause.done", "Playback paused"),
+    ("resume.done", "Playback resumed"),
+    ("volume.usage", "Usage: {prefix}volume <0-100>"),
+    ("volume.set", "Volume set to {volume}%"),
+    ("previous.done", "Playing the previous song"),
+    ("like.done", "Song saved to your library"),
+    ("queue.empty", "The queue is empty"),
+    ("queue.list", "Next up:"),
+    ("lastsong.found", "Previous song: {song}"),
+    ("lastsong.none", "No previous song found"),
+    ("history.list", "Recently played:"),
+    ("history.none", "No previous songs found"),
+    ("help.list", "Commands:"),
+    ("addcmd.usage", "Usage: {prefix}addcmd <name> <response>"),
+    ("addcmd.done", "Added {prefix}{command}"),
+    ("editcmd.usage", "Usage: {prefix}editcmd <name> <response>"),
+    ("editcmd.done", "Updated {prefix}{command}"),
+    ("delcmd.usage", "Usage: {prefix}delcmd <name>"),
+    ("delcmd.done", "Deleted {prefix}{command}"),
+    (
+        "customcmd.built_in",
+        "{prefix}{command} is a built-in command",
+    ),
+    (
+        "customcmd.exists",
+        "{prefix}{command} already exists, use {prefix}editcmd to change it",
+    ),
+    ("customcmd.missing", "There is no command {prefix}{command}"),
+    (
+        "timer.usage",
+        "Usage: {prefix}timer add <name> <minutes> <min chat lines> <message> | {prefix}timer remove <name>",
+    ),
+    (
+        "timer.added",
+        "Added timer {name}, posting every {minutes} minutes while live",
+    ),
+    ("timer.removed", "Removed timer {name}"),
+    ("timer.missing", "There is no timer {name}"),
+];
+
+const SV: &[(&str, &str)] = &[
+    (
+        "cooldown",
+        "{prefix}{command} kan användas igen om {seconds}s",
+    ),
+    (
+        "unavailable",
+        "Spotify-kommandon är inte tillgängliga just nu, försök igen om en stund",
+    ),
+    (
+        "request.failed",
+        "Det gick inte, förfrågan misslyckades med statuskod {status}",
+    ),
+    ("now_playing", "Spelas nu: {song}"),
+    ("song.not_playing", "Ingen låt spelas just nu"),
+    ("songlink.local", "{title} är en lokal fil och har ingen länk"),
+    (
+        "skip.vote_cooldown",
+        "Röstning om att skippa öppnar igen om {seconds}s",
+    ),
+    ("skip.votes", "{votes}/{required} röster för att skippa {title}"),
+    ("skip.vote_passed", "Röstningen gick igenom, låten skippas"),
+    ("skip.skipped", "Låten skippades"),
+    ("skip.no_vote", "Ingen röstning om att skippa pågår"),
+    ("skip.vetoed", "Röstningen avbröts"),
+    ("skip.status", "{votes}/{required} röster för att skippa"),
+    (
+        "skipon.usage",
+        "Användning: {prefix}skipon [röster eller procent%] [röstens livslängd i sek] [nedkylning i sek]",
+    ),
+    ("skipon.enabled", "Röstning om att skippa är nu på"),
+    ("skipoff.disabled", "Röstning om att skippa är nu av"),
+    ("sr.usage", "Användning: {prefix}sr <låtnamn eller Spotify-länk>"),
+    ("sr.invalid_link", "Ogiltig Spotify-länk"),
+    ("sr.not_a_track", "Bara länkar till Spotify-låtar kan önskas"),
+    ("sr.disabled", "Låtönskningar är avstängda"),
+    (
+        "sr.limit",
+        "Du kan bara önska {count} låtar per {minutes} minuter",
+    ),
+    ("sr.not_found", "Ingen låt hittades"),
+    ("sr.too_long", "{title} är för lång, max är {minutes} minuter"),
+    ("sr.queued", "{song} lades i kön på plats {position}"),
+    ("pause.done", "Uppspelningen är pausad"),
+    ("resume.done", "Uppspelningen är återupptagen"),
+    ("volume.usage", "Användning: {prefix}volume <0-100>"),
+    ("volume.set", "Volymen är satt till {volume}%"),
+    ("previous.done", "Spelar föregående låt"),
+    ("like.done", "Låten sparades i ditt bibliotek"),
+    ("queue.empty", "Kön är tom"),
+    ("queue.list", "Näst på tur:"),
+    ("lastsong.found", "Föregående låt: {song}"),
+    ("lastsong.none", "Ingen föregående låt hittades"),
+    ("history.list", "Nyligen spelat:"),
+    ("history.none", "Inga tidigare låtar hittades"),
+    ("help.list", "Kommandon:"),
+    ("addcmd.usage", "Användning: {prefix}addcmd <namn> <svar>"),
+    ("addcmd.done", "Lade till {prefix}{command}"),
+    ("editcmd.usage", "Användning: {prefix}editcmd <namn> <svar>"),
+    ("editcmd.done", "Uppdaterade {prefix}{command}"),
+    ("delcmd.usage", "Användning: {prefix}delcmd <namn>"),
+    ("delcmd.done", "Tog bort {prefix}{command}"),
+    ("customcmd.built_in", "{prefix}{command} är ett inbyggt kommando"),
+    (
+        "customcmd.exists",
+        "{prefix}{command} finns redan, använd {prefix}editcmd för att ändra det",
+    ),
+    ("customcmd.missing", "Det finns inget kommando {prefix}{command}"),
+    (
+        "timer.usage",
+        "Användning: {prefix}timer add <namn> <minuter> <minsta antal chattrader> <meddelande> | {prefix}timer remove <namn>",
+    ),
+    (
+        "timer.added",
+        "Lade till timern {name}, den skickas var {minutes}:e minut under sändning",
+    ),
+    ("timer.removed", "Tog bort timern {name}"),
+    ("timer.missing", "Det finns ingen timer {name}"),
+];
+
+/// Built-in message for `key`. Falls back to English for messages that
+/// haven't been translated, and to the key itself for unknown keys.
+pub fn template(locale: Locale, key: &str) -> &str {
+    let catalog = match locale {
+        Locale::En => EN,
+        Locale::Sv => SV,
+    };
+    lookup(catalog, key)
+        .or_else(|| lookup(EN, key))
+        .unwrap_or(key)
+}
+
+fn lookup(catalog: &[(&str, &'static str)], key: &str) -> Option<&'static str> {
+    catalog
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, template)| *template)
+}
+
+/// Replaces each {name} in `template` with its value in `args`. Unknown
+/// placeholders are left as they are, and values are never substituted
+/// again, so a song title can't inject placeholders of its own.
+pub fn format(template: &str, args: &[(&str, &str)]) -> String {
+    let mut message = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        message.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find('}').and_then(|end| {
+            let name = &rest[1..end];
+            args.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, value)| (end, *value))
+        });
+        match value {
+            Some((end, value)) => {
+                message.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                message.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    message.push_str(rest);
+
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_placeholders_once() {
+        let message = format(
+            "{votes}/{required} votes to skip {title} {unknown}",
+            &[("votes", "2"), ("required", "3"), ("title", "{votes}")],
+        );
+        assert_eq!(message, "2/3 votes to skip {votes} {unknown}");
+    }
+
+    #[test]
+    fn every_translation_has_an_english_message() {
+        for (key, _) in SV {
+            assert!(EN.iter().any(|(k, _)| k == key), "{} is not in EN", key);
+        }
+        assert_eq!(template(Locale::Sv, "skip.skipped"), "Låten skippades");
+        assert_eq!(template(Locale::Sv, "missing"), "missing");
+    }
+}
@@ -0,0 +1,49 @@
+/// A NOTICE sent by Twitch, identified by its `msg-id` tag.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notice {
+    Banned,
+    TimedOut,
+    RateLimited,
+    Duplicate,
+    ChannelSuspended,
+    LoginFailed,
+    Other(String),
+}
+
+impl Notice {
+    pub fn parse(msg_id: Option<&str>, message: &str) -> Notice {
+        match msg_id {
+            Some("msg_banned") => Notice::Banned,
+            Some("msg_timedout") => Notice::TimedOut,
+            Some("msg_ratelimit") => Notice::RateLimited,
+            Some("msg_duplicate") => Notice::Duplicate,
+            Some("msg_channel_suspended") => Notice::ChannelSuspended,
+            Some(id) => Notice::Other(id.to_string()),
+            // Login failures are sent without a msg-id
+            None if is_login_failure(message) => Notice::LoginFailed,
+            None => Notice::Other(String::new()),
+        }
+    }
+
+    pub fn msg_id(&self) -> &str {
+        match self {
+            Notice::Banned => "msg_banned",
+            Notice::TimedOut => "msg_timedout",
+            Notice::RateLimited => "msg_ratelimit",
+            Notice::Duplicate => "msg_duplicate",
+            Notice::ChannelSuspended => "msg_channel_suspended",
+            Notice::LoginFailed => "login_failed",
+            Notice::Other(id) => id,
+        }
+    }
+
+    /// Whether the bot should leave the channel after receiving this notice.
+    pub fn should_part(&self) -> bool {
+        matches!(self, Notice::Banned | Notice::ChannelSuspended)
+    }
+}
+
+fn is_login_failure(message: &str) -> bool {
+    message.starts_with("Login authentication failed")
+        || message.starts_with("Improperly formatted auth")
+}
@@ -0,0 +1,101 @@
+use crate::channel_state::ChannelStates;
+use crate::settings::Settings;
+use crate::song_cache::SongCache;
+use crate::web::WebClient;
+use crate::{ReaderAction, ReaderActionEvent};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+use tokio::time::sleep;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Now playing announcement settings of a channel.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NowPlayingSettings {
+    pub enabled: bool,
+    /// Minimum seconds between announcements, so rapid skips don't flood chat
+    pub min_interval: u64,
+}
+
+impl Default for NowPlayingSettings {
+    fn default() -> Self {
+        NowPlayingSettings {
+            enabled: false,
+            min_interval: 30,
+        }
+    }
+}
+
+struct Announced {
+    track_id: String,
+    at: Option<Instant>,
+}
+
+/// Polls the current track of every joined channel that has announcements
+/// enabled and posts "Now playing: ..." when it changes. A change within
+/// `min_interval` of the last announcement is held back, and only the track
+/// playing once the interval has passed is announced.
+pub async fn start_now_playing(
+    tx: Sender<ReaderAction>,
+    channel_states: ChannelStates,
+    songs: Arc<SongCache>,
+    settings: Arc<Settings>,
+    web: Arc<WebClient>,
+) {
+    // Keyed by channel name with a # in front
+    let mut announced: HashMap<String, Announced> = HashMap::new();
+
+    loop {
+        sleep(POLL_INTERVAL).await;
+        let channels: Vec<String> = channel_states.lock().unwrap().keys().cloned().collect();
+        for channel in channels {
+            let channel_name = &channel[1..];
+            let channel_settings = settings.get(&web, channel_name).await;
+            if !channel_settings.now_playing.enabled {
+                announced.remove(&channel);
+                continue;
+            }
+            let song = match songs.get(&web, channel_name).await {
+                Ok(song) => song,
+                Err(e) => {
+                    eprintln!("[ERROR] Could not get song for {}: {}", channel, e);
+                    continue;
+                }
+            };
+            if !song.is_playing {
+                continue;
+            }
+
+            let track_id = song.item.track_id();
+            let Some(last) = announced.get_mut(&channel) else {
+                // Don't announce whatever was already playing when the bot started
+                announced.insert(channel, Announced { track_id, at: None });
+                continue;
+            };
+            if last.track_id == track_id {
+                continue;
+            }
+            let min_interval = Duration::from_secs(channel_settings.now_playing.min_interval);
+            if last.at.is_some_and(|at| at.elapsed() < min_interval) {
+                continue;
+            }
+            last.track_id = track_id;
+            last.at = Some(Instant::now());
+
+            tx.send(ReaderAction {
+                event: ReaderActionEvent::Message,
+                message: Some(format!(
+                    "PRIVMSG {} :{}",
+                    channel,
+                    channel_settings.message("now_playing", &[("song", &song.item.title())])
+                )),
+            })
+            .await
+            .unwrap_or_else(|e| eprintln!("[ERROR] Sender Error: {:?}", e));
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::channel_state::{ChannelStates, SendDecision};
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+// Appended to a message to get past Twitch's duplicate message check
+const DUPLICATE_SUFFIX: &str = " \u{E0000}";
+
+/// Chat messages waiting for a channel's slow mode or a rate limit backoff.
+/// Each channel's messages are sent in the order they were queued.
+#[derive(Default)]
+pub struct Outbox {
+    /// Keyed by channel name with a # in front
+    pending: HashMap<String, VecDeque<String>>,
+    paused_until: Option<Instant>,
+}
+
+impl Outbox {
+    pub fn push(&mut self, channel: &str, msg: String) {
+        self.pending
+            .entry(channel.to_string())
+            .or_default()
+            .push_back(msg);
+    }
+
+    /// Holds every chat message back until `until`.
+    pub fn pause_until(&mut self, until: Instant) {
+        self.paused_until = Some(until);
+    }
+
+    /// Takes the messages that can be sent now, in order, and marks them as
+    /// sent. Also returns when messages still waiting can be sent, if any are.
+    pub fn take_ready(
+        &mut self,
+        channel_states: &ChannelStates,
+        now: Instant,
+    ) -> (Vec<String>, Option<Instant>) {
+        if let Some(until) = self.paused_until {
+            if until > now {
+                return (Vec::new(), (!self.pending.is_empty()).then_some(until));
+            }
+            self.paused_until = None;
+        }
+
+        let mut ready = Vec::new();
+        let mut next_check: Option<Instant> = None;
+        let mut channel_states = channel_states.lock().unwrap();
+        self.pending.retain(|channel, queue| {
+            // The bot isn't in the channel, or parted from it after the messages were queued
+            let Some(state) = channel_states.get_mut(channel) else {
+                println!("[INFO] Not in {}, not sending: {:?}", channel, queue);
+                return false;
+            };
+            while let Some(msg) = queue.front() {
+                match state.check_send(now) {
+                    SendDecision::Send => {
+                        let mut msg = queue.pop_front().unwrap();
+                        if state.is_r9k_duplicate(&msg) {
+                            msg = alter_duplicate(&msg);
+                        }
+                        state.last_sent = Some((now, msg.clone()));
+                        ready.push(msg);
+                    }
+                    SendDecision::Drop(reason) => {
+                        println!("[INFO] Not sending to {}, {}: {}", channel, reason, msg);
+                        queue.pop_front();
+                    }
+                    SendDecision::Delay(delay) => {
+                        let at = now + delay;
+                        next_check = Some(next_check.map_or(at, |n| n.min(at)));
+                        break;
+                    }
+                }
+            }
+            !queue.is_empty()
+        });
+
+        (ready, next_check)
+    }
+}
+
+/// Alters a message so Twitch doesn't reject it as a duplicate of the last one.
+pub fn alter_duplicate(msg: &str) -> String {
+    match msg.strip_suffix(DUPLICATE_SUFFIX) {
+        Some(m) => m.to_string(),
+        None => format!("{}{}", msg, DUPLICATE_SUFFIX),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel_state::ChannelState;
+    use std::time::Duration;
+
+    fn channel_states(slow: Duration) -> ChannelStates {
+        let states = ChannelStates::default();
+        states.lock().unwrap().insert(
+            "#a".to_string(),
+            ChannelState {
+                slow,
+                ..Default::default()
+            },
+        );
+        states
+    }
+
+    #[test]
+    fn slow_mode_sends_in_order() {
+        let states = channel_states(Duration::from_secs(3));
+        let mut outbox = Outbox::default();
+        let now = Instant::now();
+        for msg in ["PRIVMSG #a :1", "PRIVMSG #a :2", "PRIVMSG #a :3"] {
+            outbox.push("#a", msg.to_string());
+        }
+
+        let (ready, next) = outbox.take_ready(&states, now);
+        assert_eq!(ready, vec!["PRIVMSG #a :1"]);
+        assert_eq!(next, Some(now + Duration::from_secs(3)));
+
+        let later = now + Duration::from_secs(3);
+        assert_eq!(outbox.take_ready(&states, later).0, vec!["PRIVMSG #a :2"]);
+        let later = later + Duration::from_secs(3);
+        assert_eq!(outbox.take_ready(&states, later).0, vec!["PRIVMSG #a :3"]);
+        assert_eq!(outbox.take_ready(&states, later), (Vec::new(), None));
+    }
+
+    #[test]
+    fn drops_messages_to_unknown_channels() {
+        let states = channel_states(Duration::ZERO);
+        let mut outbox = Outbox::default();
+        outbox.push("#b", "PRIVMSG #b :hi".to_string());
+        outbox.push("#a", "PRIVMSG #a :hi".to_string());
+
+        let (ready, next) = outbox.take_ready(&states, Instant::now());
+        assert_eq!(ready, vec!["PRIVMSG #a :hi"]);
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn pause_holds_back_messages() {
+        let states = channel_states(Duration::ZERO);
+        let mut outbox = Outbox::default();
+        let now = Instant::now();
+        let until = now + Duration::from_secs(2);
+        outbox.pause_until(until);
+        outbox.push("#a", "PRIVMSG #a :hi".to_string());
+
+        assert_eq!(outbox.take_ready(&states, now), (Vec::new(), Some(until)));
+        assert_eq!(outbox.take_ready(&states, until).0, vec!["PRIVMSG #a :hi"]);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::commands::{ChannelCooldowns, CustomCommand, SongRequestSettings, SongSettings};
+use crate::messages::{self, Locale};
+use crate::now_playing::NowPlayingSettings;
+use crate::timers::Timer;
+use crate::vote_skip::SkipSettings;
+use crate::web::WebClient;
+use futures::StreamExt;
+use lapin::options::{BasicAckOptions, BasicConsumeOptions, QueueDeclareOptions};
+use lapin::types::FieldTable;
+use lapin::Channel;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::sleep;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+// stbot-web publishes the name of a channel to this queue when its settings change
+const SETTINGS_QUEUE: &str = "settings";
+
+/// Everything configurable for a channel in stbot-web.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ChannelSettings {
+    /// Prefix of chat commands, like the ? in ?song
+    pub prefix: String,
+    pub locale: Locale,
+    /// Replaces the built-in messages of the locale, keyed by message name
+    pub messages: HashMap<String, String>,
+    /// Names of commands that are turned off in the channel
+    pub disabled_commands: Vec<String>,
+    pub custom_commands: Vec<CustomCommand>,
+    pub skip: SkipSettings,
+    pub song: SongSettings,
+    pub song_request: SongRequestSettings,
+    pub cooldowns: ChannelCooldowns,
+    pub now_playing: NowPlayingSettings,
+    pub timers: Vec<Timer>,
+}
+
+impl Default for ChannelSettings {
+    fn default() -> Self {
+        ChannelSettings {
+            prefix: "?".to_string(),
+            locale: Locale::default(),
+            messages: HashMap::new(),
+            disabled_commands: Vec::new(),
+            custom_commands: Vec::new(),
+            skip: SkipSettings::default(),
+            song: SongSettings::default(),
+            song_request: SongRequestSettings::default(),
+            cooldowns: ChannelCooldowns::default(),
+            now_playing: NowPlayingSettings::default(),
+            timers: Vec::new(),
+        }
+    }
+}
+
+impl ChannelSettings {
+    pub fn is_disabled(&self, command_name: &str) -> bool {
+        self.disabled_commands.iter().any(|c| c == command_name)
+    }
+
+    /// The channel's version of the message named `key`, with its placeholders
+    /// and {prefix} filled in.
+    pub fn message(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let template = match self.messages.get(key) {
+            Some(template) => template,
+            None => messages::template(self.locale, key),
+        };
+        let mut args = args.to_vec();
+        args.push(("prefix", &self.prefix));
+        messages::format(template, &args)
+    }
+}
+
+/// Settings of every channel the bot has seen, keyed by channel name without
+/// a # in front. Fetched the first time they are needed and kept up to date
+/// by `start_settings_refresh` and `start_settings_consumer`.
+#[derive(Default)]
+pub struct Settings {
+    channels: Mutex<HashMap<String, Arc<ChannelSettings>>>,
+}
+
+impl Settings {
+    pub async fn get(&self, web: &WebClient, channel_name: &str) -> Arc<ChannelSettings> {
+        if let Some(settings) = self.channels.lock().unwrap().get(channel_name) {
+            return settings.clone();
+        }
+        self.refresh(web, channel_name).await
+    }
+
+    /// Fetches the channel's settings again. Keeps the last known settings (or
+    /// the defaults) if stbot-web can't be reached, until the next refresh.
+    pub async fn refresh(&self, web: &WebClient, channel_name: &str) -> Arc<ChannelSettings> {
+        let fetched = web
+            .settings::<ChannelSettings>("/api/channels/settings", channel_name)
+            .await;
+        let mut channels = self.channels.lock().unwrap();
+        match fetched {
+            Ok(settings) => {
+                let settings = Arc::new(settings);
+                channels.insert(channel_name.to_string(), settings.clone());
+                settings
+            }
+            Err(e) => {
+                eprintln!("[ERROR] Could not get settings for {}: {}", channel_name, e);
+                channels
+                    .entry(channel_name.to_string())
+                    .or_default()
+                    .clone()
+            }
+        }
+    }
+
+    fn channel_names(&self) -> Vec<String> {
+        self.channels.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+/// Refreshes the settings of every known channel periodically, in case a
+/// change notification was missed.
+pub async fn start_settings_refresh(settings: Arc<Settings>, web: Arc<WebClient>) {
+    loop {
+        sleep(REFRESH_INTERVAL).await;
+        for channel_name in settings.channel_names() {
+            settings.refresh(&web, &channel_name).await;
+        }
+    }
+}
+
+/// Refreshes a channel's settings as soon as stbot-web reports they changed.
+pub async fn start_settings_consumer(
+    channel: Channel,
+    settings: Arc<Settings>,
+    web: Arc<WebClient>,
+) {
+    let declared = channel
+        .queue_declare(
+            SETTINGS_QUEUE,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await;
+    let consumer = match declared {
+        Ok(_) => {
+            channel
+                .basic_consume(
+                    SETTINGS_QUEUE,
+                    "bot-settings",
+                    BasicConsumeOptions::default(),
+                    FieldTable::default(),
+                )
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    let mut consumer = match consumer {
+        Ok(consumer) => consumer,
+        Err(e) => {
+            eprintln!(
+                "[ERROR] Could not listen for settings changes, only polling for them: {:?}",
+                e
+            );
+            return;
+        }
+    };
+
+    while let Some(delivery) = consumer.next().await {
+        let delivery = match delivery {
+            Ok(delivery) => delivery,
+            Err(e) => {
+                eprintln!("[ERROR] Error in settings consumer: {:?}", e);
+                continue;
+            }
+        };
+        match str::from_utf8(&delivery.data) {
+            Ok(channel_name) => {
+                let channel_name = channel_name.trim().trim_start_matches('#');
+                println!("[INFO] Settings changed for {}", channel_name);
+                settings.refresh(&web, channel_name).await;
+            }
+            Err(e) => eprintln!("[ERROR] Invalid settings notification: {:?}", e),
+        }
+        delivery
+            .ack(BasicAckOptions::default())
+            .await
+            .unwrap_or_else(|e| eprintln!("[ERROR] Could not ack settings notification: {:?}", e));
+    }
+}
@@ -0,0 +1,60 @@
+use crate::song_history::SongHistory;
+use crate::web::{SongResponse, WebClient, WebError};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type CachedSong = Arc<tokio::sync::Mutex<Option<(Instant, Arc<SongResponse>)>>>;
+
+/// Current song of each channel, cached for `ttl` so that commands used at the
+/// same time share one stbot-web request. Every fetched song is recorded in
+/// the song history.
+pub struct SongCache {
+    ttl: Duration,
+    history: Arc<SongHistory>,
+    /// Keyed by channel name without a # in front
+    channels: Mutex<HashMap<String, CachedSong>>,
+}
+
+impl SongCache {
+    pub fn new(ttl: Duration, history: Arc<SongHistory>) -> SongCache {
+        SongCache {
+            ttl,
+            history,
+            channels: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached song, or fetches it. Lookups for a channel while a
+    /// fetch is in flight wait for it instead of sending their own request.
+    pub async fn get(
+        &self,
+        web: &WebClient,
+        channel_name: &str,
+    ) -> Result<Arc<SongResponse>, WebError> {
+        let cached = self
+            .channels
+            .lock()
+            .unwrap()
+            .entry(channel_name.to_string())
+            .or_default()
+            .clone();
+        let mut cached = cached.lock().await;
+        if let Some((fetched_at, song)) = cached.as_ref() {
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(song.clone());
+            }
+        }
+
+        let song = Arc::new(web.current_song(channel_name).await?);
+        self.history.record(channel_name, &song);
+        *cached = Some((Instant::now(), song.clone()));
+
+        Ok(song)
+    }
+
+    /// Drops the cached song after playback was changed from chat.
+    pub fn invalidate(&self, channel_name: &str) {
+        self.channels.lock().unwrap().remove(channel_name);
+    }
+}
@@ -0,0 +1,46 @@
+use crate::web::SongResponse;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+const MAX_HISTORY_LENGTH: usize = 20;
+
+struct HistoryEntry {
+    track_id: String,
+    title: String,
+}
+
+/// Tracks the bot has seen playing, most recent first, keyed by channel name
+/// without a # in front.
+#[derive(Default)]
+pub struct SongHistory {
+    channels: Mutex<HashMap<String, VecDeque<HistoryEntry>>>,
+}
+
+impl SongHistory {
+    pub fn record(&self, channel_name: &str, song: &SongResponse) {
+        if !song.is_playing {
+            return;
+        }
+        let mut channels = self.channels.lock().unwrap();
+        let history = channels.entry(channel_name.to_string()).or_default();
+        let track_id = song.item.track_id();
+        if history.front().is_some_and(|e| e.track_id == track_id) {
+            return;
+        }
+        history.push_front(HistoryEntry {
+            track_id,
+            title: song.item.title(),
+        });
+        history.truncate(MAX_HISTORY_LENGTH);
+    }
+
+    /// Titles of the observed tracks, most recent first.
+    pub fn recent(&self, channel_name: &str) -> Vec<String> {
+        self.channels
+            .lock()
+            .unwrap()
+            .get(channel_name)
+            .map(|h| h.iter().map(|e| e.title.clone()).collect())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,117 @@
+use crate::channel_state::ChannelStates;
+use crate::settings::Settings;
+use crate::web::WebClient;
+use crate::{ReaderAction, ReaderActionEvent};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+use tokio::time::sleep;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A message posted to a channel periodically.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Timer {
+    pub name: String,
+    pub message: String,
+    /// Minimum seconds between posts
+    pub interval: u64,
+    /// Chat messages that must have been sent since the last post, so the
+    /// timer doesn't fill a quiet chat on its own
+    pub min_lines: u64,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Timer {
+            name: String::new(),
+            message: String::new(),
+            interval: 900,
+            min_lines: 5,
+        }
+    }
+}
+
+struct Posted {
+    at: Instant,
+    /// Chat lines of the channel at the time
+    chat_lines: u64,
+}
+
+/// Posts the timers of every joined channel that is live. Timers start
+/// counting when the bot joins or the stream goes live, so nothing is posted
+/// right away.
+pub async fn start_timers(
+    tx: Sender<ReaderAction>,
+    channel_states: ChannelStates,
+    settings: Arc<Settings>,
+    web: Arc<WebClient>,
+) {
+    // Keyed by channel name with a # in front, then by timer name
+    let mut posted: HashMap<String, HashMap<String, Posted>> = HashMap::new();
+
+    loop {
+        sleep(POLL_INTERVAL).await;
+        let channels: Vec<String> = channel_states.lock().unwrap().keys().cloned().collect();
+        for channel in channels {
+            let channel_name = &channel[1..];
+            let timers = &settings.get(&web, channel_name).await.timers;
+            if timers.is_empty() {
+                posted.remove(&channel);
+                continue;
+            }
+            let is_live = match web.is_live(channel_name).await {
+                Ok(is_live) => is_live,
+                Err(e) => {
+                    eprintln!("[ERROR] Could not get stream status of {}: {}", channel, e);
+                    false
+                }
+            };
+            let Some(chat_lines) = channel_states
+                .lock()
+                .unwrap()
+                .get(&channel)
+                .map(|s| s.chat_lines)
+            else {
+                continue;
+            };
+
+            let now = Instant::now();
+            let channel_posted = posted.entry(channel.clone()).or_default();
+            channel_posted.retain(|name, _| timers.iter().any(|t| &t.name == name));
+            for timer in timers {
+                let last = channel_posted.entry(timer.name.clone()).or_insert(Posted {
+                    at: now,
+                    chat_lines,
+                });
+                if !is_live {
+                    *last = Posted {
+                        at: now,
+                        chat_lines,
+                    };
+                    continue;
+                }
+                let interval = Duration::from_secs(timer.interval);
+                if now.duration_since(last.at) < interval
+                    || chat_lines.saturating_sub(last.chat_lines) < timer.min_lines
+                {
+                    continue;
+                }
+                *last = Posted {
+                    at: now,
+                    chat_lines,
+                };
+
+                tx.send(ReaderAction {
+                    event: ReaderActionEvent::Message,
+                    message: Some(format!("PRIVMSG {} :{}", channel, timer.message)),
+                })
+                .await
+                .unwrap_or_else(|e| eprintln!("[ERROR] Sender Error: {:?}", e));
+            }
+        }
+    }
+}
@@ -0,0 +1,444 @@
+use crate::message_parser::MessageResponse;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Chatters who haven't sent a message for this long are no longer counted as active
+const ACTIVE_CHATTER_WINDOW: Duration = Duration::from_secs(600);
+/// Longest vote lifetime and cooldown, in seconds. Longer values from
+/// stbot-web are treated as this long
+pub const MAX_SKIP_SECS: u64 = 3600;
+
+/// Vote skip settings of a channel.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SkipSettings {
+    /// Turned on with ?skipon and off with ?skipoff
+    pub enabled: bool,
+    /// Number of votes required to skip
+    pub required_votes: u32,
+    /// Percentage of active chatters required to skip, used instead of
+    /// `required_votes` when set
+    pub required_percentage: Option<u32>,
+    /// Seconds a vote counts towards a skip
+    pub vote_lifetime: u64,
+    /// Seconds after a skip before voting opens again
+    pub cooldown: u64,
+}
+
+impl Default for SkipSettings {
+    fn default() -> Self {
+        SkipSettings {
+            enabled: false,
+            required_votes: 5,
+            required_percentage: None,
+            vote_lifetime: 30,
+            cooldown: 10,
+        }
+    }
+}
+
+impl SkipSettings {
+    pub fn required_votes(&self, active_chatters: usize) -> usize {
+        match self.required_percentage {
+            Some(p) => (active_chatters * p as usize).div_ceil(100).max(1),
+            None => self.required_votes.max(1) as usize,
+        }
+    }
+
+    pub fn vote_lifetime(&self) -> Duration {
+        Duration::from_secs(self.vote_lifetime.min(MAX_SKIP_SECS))
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.cooldown.min(MAX_SKIP_SECS))
+    }
+}
+
+/// Vote skip state shared between the websocket loop and the skip commands.
+#[derive(Default)]
+pub struct VoteSkip {
+    pub sessions: Mutex<VoteSessions>,
+    pub active_chatters: Mutex<ActiveChatters>,
+}
+
+impl VoteSkip {
+    /// Votes required to skip in the channel (with a # in front).
+    pub fn required_votes(&self, channel: &str, settings: &SkipSettings) -> usize {
+        settings.required_votes(self.active_chatters.lock().unwrap().count(channel))
+    }
+}
+
+/// Vote skip state of a single channel. Every vote carries its own expiry
+/// deadline, so expired votes are dropped whenever the session is read
+/// instead of needing a task per vote.
+#[derive(Default, Debug)]
+struct VoteSession {
+    /// Lowercase usernames and when their vote expires
+    votes: HashMap<String, Instant>,
+    last_skip: Option<Instant>,
+    /// Track the current votes are for
+    track_id: Option<String>,
+}
+
+impl VoteSession {
+    fn expire(&mut self, now: Instant) {
+        self.votes.retain(|_, expires_at| *expires_at > now);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum VoteOutcome {
+    /// Voting is closed after a recent skip
+    OnCooldown(Duration),
+    AlreadyVoted {
+        votes: usize,
+        required: usize,
+    },
+    Counted {
+        votes: usize,
+        required: usize,
+    },
+}
+
+/// Vote skip sessions keyed by channel name with a # in front.
+#[derive(Default)]
+pub struct VoteSessions {
+    channels: HashMap<String, VoteSession>,
+}
+
+impl VoteSessions {
+    /// Adds a vote to skip `track_id`. Votes for a previous track are discarded.
+    pub fn vote(
+        &mut self,
+        channel: &str,
+        track_id: &str,
+        username: &str,
+        settings: &SkipSettings,
+        active_chatters: usize,
+        now: Instant,
+    ) -> VoteOutcome {
+        let session = self.channels.entry(channel.to_string()).or_default();
+        if let Some(last_skip) = session.last_skip {
+            let since_skip = now.saturating_duration_since(last_skip);
+            if since_skip < settings.cooldown() {
+                return VoteOutcome::OnCooldown(settings.cooldown() - since_skip);
+            }
+        }
+        if session.track_id.as_deref() != Some(track_id) {
+            session.votes.clear();
+            session.track_id = Some(track_id.to_string());
+        }
+        session.expire(now);
+
+        let required = settings.required_votes(active_chatters);
+        let username = username.to_lowercase();
+        if session.votes.contains_key(&username) {
+            return VoteOutcome::AlreadyVoted {
+                votes: session.votes.len(),
+                required,
+            };
+        }
+        session
+            .votes
+            .insert(username, now + settings.vote_lifetime());
+
+        VoteOutcome::Counted {
+            votes: session.votes.len(),
+            required,
+        }
+    }
+
+    /// Clears the votes after the track was skipped and starts the cooldown.
+    pub fn skipped(&mut self, channel: &str, now: Instant) {
+        let session = self.channels.entry(channel.to_string()).or_default();
+        session.votes.clear();
+        session.track_id = None;
+        session.last_skip = Some(now);
+    }
+
+    /// Cancels the votes in progress, returning whether there were any.
+    pub fn veto(&mut self, channel: &str, now: Instant) -> bool {
+        let Some(session) = self.channels.get_mut(channel) else {
+            return false;
+        };
+        session.expire(now);
+        let had_votes = !session.votes.is_empty();
+        session.votes.clear();
+
+        had_votes
+    }
+
+    /// Number of unexpired votes in the channel to skip `track_id`. Votes
+    /// for a previous track are discarded.
+    pub fn votes(&mut self, channel: &str, track_id: &str, now: Instant) -> usize {
+        let Some(session) = self.channels.get_mut(channel) else {
+            return 0;
+        };
+        if session.track_id.as_deref() != Some(track_id) {
+            session.votes.clear();
+        }
+        session.expire(now);
+
+        session.votes.len()
+    }
+}
+
+/// Chatters seen recently in each channel, keyed by channel name with a # in front.
+#[derive(Default)]
+pub struct ActiveChatters {
+    channels: HashMap<String, HashMap<String, Instant>>,
+}
+
+impl ActiveChatters {
+    /// Records the sender of a PRIVMSG as active in its channel.
+    pub fn record(&mut self, parsed_message: &MessageResponse) {
+        let Some(command) = &parsed_message.command else {
+            return;
+        };
+        if command.command.as_deref() != Some("PRIVMSG") {
+            return;
+        }
+        let Some(channel) = &command.channel else {
+            return;
+        };
+        let display_name = parsed_message
+            .tags
+            .as_ref()
+            .and_then(|t| t.other.as_ref())
+            .and_then(|o| o.get("display-name"));
+        let Some(display_name) = display_name else {
+            return;
+        };
+
+        let now = Instant::now();
+        let chatters = self.channels.entry(channel.clone()).or_default();
+        chatters.insert(display_name.to_lowercase(), now);
+        chatters.retain(|_, seen_at| now.duration_since(*seen_at) < ACTIVE_CHATTER_WINDOW);
+    }
+
+    pub fn count(&self, channel: &str) -> usize {
+        self.channels
+            .get(channel)
+            .map(|chatters| {
+                chatters
+                    .values()
+                    .filter(|seen_at| seen_at.elapsed() < ACTIVE_CHATTER_WINDOW)
+                    .count()
+            })
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> SkipSettings {
+        SkipSettings {
+            enabled: true,
+            required_votes: 3,
+            required_percentage: None,
+            vote_lifetime: 30,
+            cooldown: 10,
+        }
+    }
+
+    #[test]
+    fn votes_expire_after_lifetime() {
+        let mut sessions = VoteSessions::default();
+        let now = Instant::now();
+        sessions.vote("#a", "track", "user1", &settings(), 0, now);
+        sessions.vote(
+            "#a",
+            "track",
+            "user2",
+            &settings(),
+            0,
+            now + Duration::from_secs(20),
+        );
+
+        assert_eq!(
+            sessions.votes("#a", "track", now + Duration::from_secs(29)),
+            2
+        );
+        assert_eq!(
+            sessions.votes("#a", "track", now + Duration::from_secs(31)),
+            1
+        );
+        assert_eq!(
+            sessions.votes("#a", "track", now + Duration::from_secs(51)),
+            0
+        );
+    }
+
+    #[test]
+    fn expired_voter_can_vote_again() {
+        let mut sessions = VoteSessions::default();
+        let now = Instant::now();
+        sessions.vote("#a", "track", "user1", &settings(), 0, now);
+
+        let outcome = sessions.vote(
+            "#a",
+            "track",
+            "user1",
+            &settings(),
+            0,
+            now + Duration::from_secs(31),
+        );
+        assert_eq!(
+            outcome,
+            VoteOutcome::Counted {
+                votes: 1,
+                required: 3
+            }
+        );
+    }
+
+    #[test]
+    fn duplicate_votes_are_not_counted() {
+        let mut sessions = VoteSessions::default();
+        let now = Instant::now();
+        sessions.vote("#a", "track", "User1", &settings(), 0, now);
+
+        let outcome = sessions.vote("#a", "track", "user1", &settings(), 0, now);
+        assert_eq!(
+            outcome,
+            VoteOutcome::AlreadyVoted {
+                votes: 1,
+                required: 3
+            }
+        );
+        assert_eq!(sessions.votes("#a", "track", now), 1);
+    }
+
+    #[test]
+    fn votes_reset_when_track_changes() {
+        let mut sessions = VoteSessions::default();
+        let now = Instant::now();
+        sessions.vote("#a", "track1", "user1", &settings(), 0, now);
+        sessions.vote("#a", "track1", "user2", &settings(), 0, now);
+
+        let outcome = sessions.vote("#a", "track2", "user3", &settings(), 0, now);
+        assert_eq!(
+            outcome,
+            VoteOutcome::Counted {
+                votes: 1,
+                required: 3
+            }
+        );
+    }
+
+    #[test]
+    fn vote_count_resets_when_track_changes() {
+        let mut sessions = VoteSessions::default();
+        let now = Instant::now();
+        sessions.vote("#a", "track1", "user1", &settings(), 0, now);
+        sessions.vote("#a", "track1", "user2", &settings(), 0, now);
+        assert_eq!(sessions.votes("#a", "track1", now), 2);
+
+        assert_eq!(sessions.votes("#a", "track2", now), 0);
+        assert_eq!(sessions.votes("#a", "track1", now), 0);
+    }
+
+    #[test]
+    fn channels_are_independent() {
+        let mut sessions = VoteSessions::default();
+        let now = Instant::now();
+        sessions.vote("#a", "track", "user1", &settings(), 0, now);
+        sessions.vote("#a", "track", "user2", &settings(), 0, now);
+        sessions.vote("#b", "track", "user1", &settings(), 0, now);
+
+        assert_eq!(sessions.votes("#a", "track", now), 2);
+        assert_eq!(sessions.votes("#b", "track", now), 1);
+
+        sessions.skipped("#a", now);
+        assert_eq!(sessions.votes("#a", "track", now), 0);
+        assert_eq!(sessions.votes("#b", "track", now), 1);
+
+        let outcome = sessions.vote("#b", "track", "user2", &settings(), 0, now);
+        assert_eq!(
+            outcome,
+            VoteOutcome::Counted {
+                votes: 2,
+                required: 3
+            }
+        );
+    }
+
+    #[test]
+    fn veto_clears_votes() {
+        let mut sessions = VoteSessions::default();
+        let now = Instant::now();
+        assert!(!sessions.veto("#a", now));
+
+        sessions.vote("#a", "track", "user1", &settings(), 0, now);
+        assert!(sessions.veto("#a", now));
+        assert!(!sessions.veto("#a", now));
+        assert_eq!(sessions.votes("#a", "track", now), 0);
+    }
+
+    #[test]
+    fn voting_reopens_after_cooldown() {
+        let mut sessions = VoteSessions::default();
+        let now = Instant::now();
+        sessions.skipped("#a", now);
+
+        let outcome = sessions.vote(
+            "#a",
+            "track",
+            "user1",
+            &settings(),
+            0,
+            now + Duration::from_secs(4),
+        );
+        assert_eq!(outcome, VoteOutcome::OnCooldown(Duration::from_secs(6)));
+
+        let outcome = sessions.vote(
+            "#a",
+            "track",
+            "user1",
+            &settings(),
+            0,
+            now + Duration::from_secs(10),
+        );
+        assert_eq!(
+            outcome,
+            VoteOutcome::Counted {
+                votes: 1,
+                required: 3
+            }
+        );
+    }
+
+    #[test]
+    fn required_votes_from_percentage() {
+        let settings = SkipSettings {
+            required_percentage: Some(10),
+            ..settings()
+        };
+        assert_eq!(settings.required_votes(0), 1);
+        assert_eq!(settings.required_votes(25), 3);
+        assert_eq!(settings.required_votes(1000), 100);
+    }
+
+    #[test]
+    fn huge_durations_are_capped() {
+        let mut sessions = VoteSessions::default();
+        let settings = SkipSettings {
+            vote_lifetime: u64::MAX,
+            cooldown: u64::MAX,
+            ..settings()
+        };
+        let now = Instant::now();
+        sessions.vote("#a", "track", "user1", &settings, 0, now);
+        sessions.skipped("#a", now);
+
+        let outcome = sessions.vote("#a", "track", "user1", &settings, 0, now);
+        assert_eq!(
+            outcome,
+            VoteOutcome::OnCooldown(Duration::from_secs(MAX_SKIP_SECS))
+        );
+    }
+}
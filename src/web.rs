@@ -0,0 +1,634 @@
+use crate::circuit_breaker::CircuitBreaker;
+use crate::config::WebConfig;
+use hmac::{Hmac, Mac};
+use reqwest::{Method, RequestBuilder, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::time::sleep;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// GETs are retried, so each attempt gets a shorter timeout
+const READ_TIMEOUT: Duration = Duration::from_secs(3);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+const READ_ATTEMPTS: u32 = 3;
+const RETRY_BACKOFF: Duration = Duration::from_millis(250);
+const BREAKER_FAILURE_THRESHOLD: u32 = 5;
+const BREAKER_OPEN_DURATION: Duration = Duration::from_secs(30);
+const METRICS_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug)]
+pub enum WebError {
+    /// `web.uri` is not a valid url
+    Config(String),
+    /// stbot-web could not be reached or didn't answer in time
+    Network(reqwest::Error),
+    /// stbot-web answered with a non-success status code
+    Status(StatusCode),
+    /// The response body didn't match the expected type
+    Decode(reqwest::Error),
+    /// Not called because stbot-web failed too often recently
+    Unavailable,
+}
+
+impl WebError {
+    /// Whether the error means stbot-web is unhealthy, as opposed to rejecting
+    /// this particular request.
+    fn is_backend_failure(&self) -> bool {
+        match self {
+            WebError::Network(_) => true,
+            WebError::Status(s) => s.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for WebError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebError::Config(e) => write!(f, "invalid web.uri: {}", e),
+            WebError::Network(e) => write!(f, "network error: {}", e),
+            WebError::Status(s) => write!(f, "status code {}", s),
+            WebError::Decode(e) => write!(f, "invalid response: {}", e),
+            WebError::Unavailable => write!(f, "stbot-web is unavailable"),
+        }
+    }
+}
+
+impl std::error::Error for WebError {}
+
+/// How requests to stbot-web are authenticated.
+pub enum WebAuth {
+    None,
+    /// Sent as `Authorization: Bearer <token>`
+    Bearer(String),
+    /// Requests are signed with an HMAC-SHA256 of the shared secret, see
+    /// `WebClient::sign`
+    Hmac(Vec<u8>),
+}
+
+impl WebAuth {
+    /// Prefers the shared secret over the bearer token when both are set.
+    pub fn from_config(config: &WebConfig) -> WebAuth {
+        if let Some(secret) = &config.shared_secret {
+            WebAuth::Hmac(secret.clone().into_bytes())
+        } else if let Some(token) = &config.api_token {
+            WebAuth::Bearer(token.clone())
+        } else {
+            WebAuth::None
+        }
+    }
+}
+
+/// Client for the stbot-web API. Holds a single connection pool, so it should
+/// be created once and shared.
+pub struct WebClient {
+    client: reqwest::Client,
+    /// Without a trailing /
+    base_url: String,
+    auth: WebAuth,
+    /// Makes nonces unique when several requests are signed in the same nanosecond
+    nonce_counter: AtomicU64,
+    breaker: Mutex<CircuitBreaker>,
+    /// Keyed by method and path, reset whenever they are logged
+    latencies: Mutex<HashMap<String, LatencyStats>>,
+}
+
+#[derive(Default)]
+struct LatencyStats {
+    calls: u32,
+    failures: u32,
+    total: Duration,
+    max: Duration,
+}
+
+impl WebClient {
+    pub fn from_config(config: &WebConfig) -> Result<WebClient, WebError> {
+        let auth = WebAuth::from_config(config);
+        if let WebAuth::None = auth {
+            println!("[INFO] No web.shared_secret or web.api_token set, stbot-web requests are unauthenticated");
+        }
+        WebClient::new(&config.uri, auth)
+    }
+
+    pub fn new(base_url: &str, auth: WebAuth) -> Result<WebClient, WebError> {
+        Url::parse(base_url).map_err(|e| WebError::Config(e.to_string()))?;
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(WRITE_TIMEOUT)
+            .build()
+            .map_err(|e| WebError::Config(e.to_string()))?;
+
+        Ok(WebClient {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth,
+            nonce_counter: AtomicU64::new(0),
+            breaker: Mutex::new(CircuitBreaker::new(
+                BREAKER_FAILURE_THRESHOLD,
+                BREAKER_OPEN_DURATION,
+            )),
+            latencies: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// False while calls are short-circuited after repeated failures.
+    pub fn is_available(&self) -> bool {
+        !self.breaker.lock().unwrap().is_open()
+    }
+
+    fn record(&self, method: &Method, path: &str, elapsed: Duration, failed: bool) {
+        let now = Instant::now();
+        let mut breaker = self.breaker.lock().unwrap();
+        if failed {
+            if !breaker.is_open() {
+                eprintln!("[ERROR] {} {} failed after {:?}", method, path, elapsed);
+            }
+            breaker.record_failure(now);
+            if breaker.is_open() {
+                eprintln!(
+                    "[ERROR] stbot-web is unhealthy, pausing calls for {:?}",
+                    BREAKER_OPEN_DURATION
+                );
+            }
+        } else {
+            breaker.record_success();
+        }
+
+        let mut latencies = self.latencies.lock().unwrap();
+        let stats = latencies.entry(format!("{} {}", method, path)).or_default();
+        stats.calls += 1;
+        stats.failures += failed as u32;
+        stats.total += elapsed;
+        stats.max = stats.max.max(elapsed);
+    }
+
+    /// Logs call counts and latencies per endpoint since the last time they were logged.
+    pub fn log_metrics(&self) {
+        let latencies = std::mem::take(&mut *self.latencies.lock().unwrap());
+        let mut endpoints: Vec<(String, LatencyStats)> = latencies.into_iter().collect();
+        endpoints.sort_by(|a, b| a.0.cmp(&b.0));
+        for (endpoint, stats) in endpoints {
+            println!(
+                "[INFO] stbot-web {}: {} calls, {} failed, avg {:?}, max {:?}",
+                endpoint,
+                stats.calls,
+                stats.failures,
+                stats.total / stats.calls.max(1),
+                stats.max
+            );
+        }
+    }
+
+    fn url(&self, path: &str, params: &[(&str, &str)]) -> Result<Url, WebError> {
+        let url = format!("{}{}", self.base_url, path);
+        if params.is_empty() {
+            return Url::parse(&url).map_err(|e| WebError::Config(e.to_string()));
+        }
+        Url::parse_with_params(&url, params).map_err(|e| WebError::Config(e.to_string()))
+    }
+
+    /// Adds the authentication headers. Signed requests carry a timestamp and
+    /// a nonce, so stbot-web can reject stale or replayed requests, and
+    /// `X-Stbot-Signature` is the hex HMAC-SHA256 of
+    /// `METHOD\npath?query\ntimestamp\nnonce\nhex sha256 of the body`.
+    fn sign(&self, request: RequestBuilder, method: &Method, url: &Url) -> RequestBuilder {
+        let secret = match &self.auth {
+            WebAuth::None => return request,
+            WebAuth::Bearer(token) => return request.bearer_auth(token),
+            WebAuth::Hmac(secret) => secret,
+        };
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let timestamp = now.as_secs().to_string();
+        let nonce = format!(
+            "{:x}{:x}",
+            now.as_nanos(),
+            self.nonce_counter.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        // Parameters are sent in the query, so the body is always empty
+        let body_hash = hex::encode(Sha256::digest(b""));
+        let payload = format!(
+            "{}\n{}\n{}\n{}\n{}",
+            method, path, timestamp, nonce, body_hash
+        );
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        request
+            .header("X-Stbot-Timestamp", timestamp)
+            .header("X-Stbot-Nonce", nonce)
+            .header("X-Stbot-Signature", signature)
+    }
+
+    /// Sends the request, retrying GETs with backoff when stbot-web fails.
+    /// Fails right away with `WebError::Unavailable` while the circuit breaker
+    /// is open.
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        params: &[(&str, &str)],
+    ) -> Result<reqwest::Response, WebError> {
+        let url = self.url(path, params)?;
+        let (attempts, timeout) = if method == Method::GET {
+            (READ_ATTEMPTS, READ_TIMEOUT)
+        } else {
+            (1, WRITE_TIMEOUT)
+        };
+
+        let mut attempt = 1;
+        loop {
+            if !self.breaker.lock().unwrap().allow(Instant::now()) {
+                return Err(WebError::Unavailable);
+            }
+            let started_at = Instant::now();
+            let request = self
+                .client
+                .request(method.clone(), url.clone())
+                .timeout(timeout);
+            let res = match self.sign(request, &method, &url).send().await {
+                Ok(res) if res.status().is_success() => Ok(res),
+                Ok(res) => Err(WebError::Status(res.status())),
+                Err(e) => Err(WebError::Network(e)),
+            };
+            let failed = matches!(&res, Err(e) if e.is_backend_failure());
+            self.record(&method, path, started_at.elapsed(), failed);
+            if !failed || attempt >= attempts {
+                return res;
+            }
+            sleep(RETRY_BACKOFF * 2u32.pow(attempt - 1)).await;
+            attempt += 1;
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T, WebError> {
+        self.send(Method::GET, path, params)
+            .await?
+            .json::<T>()
+            .await
+            .map_err(WebError::Decode)
+    }
+
+    async fn post(
+        &self,
+        path: &str,
+        params: &[(&str, &str)],
+    ) -> Result<reqwest::Response, WebError> {
+        self.send(Method::POST, path, params).await
+    }
+
+    /// Fetches per-channel settings from `path`.
+    pub async fn settings<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        channel_name: &str,
+    ) -> Result<T, WebError> {
+        self.get(path, &[("channel_name", channel_name)]).await
+    }
+
+    /// Channels the bot should join.
+    pub async fn active_users(&self) -> Result<Vec<String>, WebError> {
+        let res: ActiveUsersResponse = self.get("/api/active", &[]).await?;
+        Ok(res.users)
+    }
+
+    pub async fn report_notice(
+        &self,
+        channel_name: &str,
+        msg_id: &str,
+        message: &str,
+    ) -> Result<(), WebError> {
+        let params = [
+            ("channel_name", channel_name),
+            ("msg_id", msg_id),
+            ("message", message),
+        ];
+        self.post("/api/notices", &params).await?;
+        Ok(())
+    }
+
+    pub async fn enable_song_skip(
+        &self,
+        channel_name: &str,
+        skip_params: &[(&str, String)],
+    ) -> Result<(), WebError> {
+        let mut params = vec![("channel_name", channel_name)];
+        params.extend(skip_params.iter().map(|(k, v)| (*k, v.as_str())));
+        self.post("/api/commands/skip/add", &params).await?;
+        Ok(())
+    }
+
+    pub async fn disable_song_skip(&self, channel_name: &str) -> Result<(), WebError> {
+        self.post(
+            "/api/commands/skip/remove",
+            &[("channel_name", channel_name)],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Adds a custom command, or replaces the response of an existing one.
+    pub async fn save_custom_command(
+        &self,
+        channel_name: &str,
+        name: &str,
+        response: &str,
+    ) -> Result<(), WebError> {
+        let params = [
+            ("channel_name", channel_name),
+            ("name", name),
+            ("response", response),
+        ];
+        self.post("/api/commands/custom/save", &params).await?;
+        Ok(())
+    }
+
+    pub async fn delete_custom_command(
+        &self,
+        channel_name: &str,
+        name: &str,
+    ) -> Result<(), WebError> {
+        let params = [("channel_name", channel_name), ("name", name)];
+        self.post("/api/commands/custom/remove", &params).await?;
+        Ok(())
+    }
+
+    /// Counts a use of a custom command, returning how many times it's been used.
+    pub async fn count_custom_command(
+        &self,
+        channel_name: &str,
+        name: &str,
+    ) -> Result<u64, WebError> {
+        let params = [("channel_name", channel_name), ("name", name)];
+        let res: CountResponse = self
+            .post("/api/commands/custom/count", &params)
+            .await?
+            .json()
+            .await
+            .map_err(WebError::Decode)?;
+        Ok(res.count)
+    }
+
+    /// Adds a timer, or replaces an existing one with the same name.
+    pub async fn save_timer(
+        &self,
+        channel_name: &str,
+        name: &str,
+        message: &str,
+        interval: u64,
+        min_lines: u64,
+    ) -> Result<(), WebError> {
+        let interval = interval.to_string();
+        let min_lines = min_lines.to_string();
+        let params = [
+            ("channel_name", channel_name),
+            ("name", name),
+            ("message", message),
+            ("interval", interval.as_str()),
+            ("min_lines", min_lines.as_str()),
+        ];
+        self.post("/api/timers/save", &params).await?;
+        Ok(())
+    }
+
+    pub async fn delete_timer(&self, channel_name: &str, name: &str) -> Result<(), WebError> {
+        let params = [("channel_name", channel_name), ("name", name)];
+        self.post("/api/timers/remove", &params).await?;
+        Ok(())
+    }
+
+    /// Whether the channel is streaming right now.
+    pub async fn is_live(&self, channel_name: &str) -> Result<bool, WebError> {
+        let res: LiveResponse = self
+            .get("/api/channels/live", &[("channel_name", channel_name)])
+            .await?;
+        Ok(res.live)
+    }
+
+    pub async fn current_song(&self, channel_name: &str) -> Result<SongResponse, WebError> {
+        self.get("/api/spotify/song", &[("channel_name", channel_name)])
+            .await
+    }
+
+    async fn playback_action(&self, channel_name: &str, action: &str) -> Result<(), WebError> {
+        let path = format!("/api/spotify/{}", action);
+        self.post(&path, &[("channel_name", channel_name)]).await?;
+        Ok(())
+    }
+
+    pub async fn skip(&self, channel_name: &str) -> Result<(), WebError> {
+        self.playback_action(channel_name, "skip").await
+    }
+
+    pub async fn pause(&self, channel_name: &str) -> Result<(), WebError> {
+        self.playback_action(channel_name, "pause").await
+    }
+
+    pub async fn resume(&self, channel_name: &str) -> Result<(), WebError> {
+        self.playback_action(channel_name, "resume").await
+    }
+
+    pub async fn previous(&self, channel_name: &str) -> Result<(), WebError> {
+        self.playback_action(channel_name, "previous").await
+    }
+
+    /// Saves the current track to the broadcaster's library.
+    pub async fn like(&self, channel_name: &str) -> Result<(), WebError> {
+        self.playback_action(channel_name, "like").await
+    }
+
+    pub async fn set_volume(&self, channel_name: &str, volume: u8) -> Result<(), WebError> {
+        let volume = volume.to_string();
+        let params = [("channel_name", channel_name), ("volume", volume.as_str())];
+        self.post("/api/spotify/volume", &params).await?;
+        Ok(())
+    }
+
+    /// Searches for a track by free text, or looks it up by id if one is given.
+    pub async fn search_track(
+        &self,
+        channel_name: &str,
+        query: &str,
+        track_id: Option<&str>,
+    ) -> Result<Option<Item>, WebError> {
+        let params = match track_id {
+            Some(id) => [("channel_name", channel_name), ("track_id", id)],
+            None => [("channel_name", channel_name), ("query", query)],
+        };
+        let res: SearchResponse = self.get("/api/spotify/search", &params).await?;
+        Ok(res.track)
+    }
+
+    pub async fn queue_track(
+        &self,
+        channel_name: &str,
+        uri: &str,
+        requested_by: &str,
+    ) -> Result<QueueResponse, WebError> {
+        let params = [
+            ("channel_name", channel_name),
+            ("uri", uri),
+            ("requested_by", requested_by),
+        ];
+        self.post("/api/spotify/queue", &params)
+            .await?
+            .json::<QueueResponse>()
+            .await
+            .map_err(WebError::Decode)
+    }
+
+    pub async fn queue(&self, channel_name: &str) -> Result<Vec<Item>, WebError> {
+        let res: TracksResponse = self
+            .get("/api/spotify/queue", &[("channel_name", channel_name)])
+            .await?;
+        Ok(res.tracks)
+    }
+
+    /// Recently played tracks, most recent first, not including the current track.
+    pub async fn recent_tracks(&self, channel_name: &str) -> Result<Vec<Item>, WebError> {
+        let res: TracksResponse = self
+            .get("/api/spotify/recent", &[("channel_name", channel_name)])
+            .await?;
+        Ok(res.tracks)
+    }
+}
+
+/// Logs the stbot-web latency metrics every few minutes.
+pub async fn start_metrics_logger(web: Arc<WebClient>) {
+    loop {
+        sleep(METRICS_INTERVAL).await;
+        web.log_metrics();
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ActiveUsersResponse {
+    users: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SongResponse {
+    pub is_playing: bool,
+    #[serde(default)]
+    pub progress_ms: u64,
+    pub item: Item,
+}
+
+/// A track, a local file or a podcast episode.
+#[derive(Deserialize, Debug)]
+pub struct Item {
+    /// Missing for local files
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub uri: String,
+    #[serde(default)]
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub is_local: bool,
+    pub external_urls: ExternalUrls,
+    /// Empty for podcast episodes
+    #[serde(default)]
+    pub artists: Vec<Artist>,
+    pub album: Option<Album>,
+    /// Only set for podcast episodes
+    pub show: Option<Show>,
+}
+
+impl Item {
+    /// Identifies the track, also when it is a local file without an id.
+    pub fn track_id(&self) -> String {
+        self.id.clone().unwrap_or_else(|| self.name.clone())
+    }
+
+    /// All artists of a track, or the show a podcast episode belongs to.
+    pub fn artist_names(&self) -> String {
+        if self.artists.is_empty() {
+            return self
+                .show
+                .as_ref()
+                .map(|s| s.name.clone())
+                .unwrap_or_default();
+        }
+        self.artists
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ")
+    }
+
+    pub fn album_name(&self) -> &str {
+        self.album
+            .as_ref()
+            .map(|a| a.name.as_str())
+            .unwrap_or_default()
+    }
+
+    pub fn title(&self) -> String {
+        format!("{} - {}", self.artist_names(), self.name)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ExternalUrls {
+    /// Missing for local files
+    pub spotify: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Artist {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Album {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Show {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct SearchResponse {
+    track: Option<Item>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LiveResponse {
+    live: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct CountResponse {
+    count: u64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct QueueResponse {
+    pub position: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct TracksResponse {
+    tracks: Vec<Item>,
+}